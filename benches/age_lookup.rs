@@ -0,0 +1,63 @@
+//! Bulk age estimation: the cached binary-search index against the old approach
+//! of rebuilding and sorting the table on every call.
+//!
+//! Run with `cargo bench --bench age_lookup`.
+
+use std::{collections::HashMap, hint::black_box, time::Instant};
+
+use telegram_id::age::{age_index, dataset::dataset};
+
+const LOOKUPS: u64 = 100_000;
+
+/// The pre-index lookup: rebuild the map, sort its keys and scan linearly.
+fn rebuild_and_scan(user_id: u64) -> u64 {
+    let ages: HashMap<u64, u64> = dataset()
+        .points
+        .iter()
+        .map(|point| (point.id, point.timestamp_ms))
+        .collect();
+    let mut sorted_ids: Vec<u64> = ages.keys().cloned().collect();
+    sorted_ids.sort();
+
+    let mut lid = sorted_ids[0];
+    for &nid in &sorted_ids {
+        if user_id <= nid {
+            let (lage, uage) = (ages[&lid] as f64, ages[&nid] as f64);
+            let span = (nid - lid) as f64;
+            let ratio = if span > 0.0 { (user_id - lid) as f64 / span } else { 0.0 };
+            return (lage + ratio * (uage - lage)) as u64;
+        }
+        lid = nid;
+    }
+    ages[&lid]
+}
+
+fn bench(name: &str, mut estimate: impl FnMut(u64) -> u64) {
+    let points = &dataset().points;
+    let (min_id, max_id) = (points[0].id, points[points.len() - 1].id);
+    let step = (max_id - min_id) / LOOKUPS;
+
+    let start = Instant::now();
+    let mut checksum = 0u64;
+    for i in 0..LOOKUPS {
+        checksum = checksum.wrapping_add(estimate(black_box(min_id + i * step)));
+    }
+    let elapsed = start.elapsed();
+
+    println!(
+        "{:<18} {:>10.2?} total  {:>8.0} ns/lookup  (checksum {})",
+        name,
+        elapsed,
+        elapsed.as_nanos() as f64 / LOOKUPS as f64,
+        checksum
+    );
+}
+
+fn main() {
+    // Build the index up front so its one-time cost is not part of the run
+    age_index();
+
+    println!("{} lookups over {} data points", LOOKUPS, dataset().points.len());
+    bench("rebuild_and_scan", rebuild_and_scan);
+    bench("age_index", |user_id| age_index().estimate(user_id).date.timestamp_millis() as u64);
+}
@@ -0,0 +1,273 @@
+use std::{fmt, sync::OnceLock};
+use chrono::{DateTime, Datelike, Duration, TimeZone, Utc};
+use serde::Serialize;
+
+pub mod dataset;
+pub mod extrapolate;
+pub mod repair;
+
+use dataset::{AgeDataset, DataPoint};
+use extrapolate::{issuance_index, GrowthModel};
+
+/// Brackets narrower than this are reported with high confidence.
+const HIGH_CONFIDENCE_SPAN_DAYS: i64 = 90;
+/// Brackets narrower than this are reported with medium confidence.
+const MEDIUM_CONFIDENCE_SPAN_DAYS: i64 = 365;
+/// Extrapolated dates are bracketed by this fraction of the time elapsed since
+/// the newest data point, so the error bars widen the further out we go.
+const EXTRAPOLATION_UNCERTAINTY: f64 = 0.25;
+/// Smallest half-width of the bracket around an extrapolated date.
+const MIN_EXTRAPOLATION_UNCERTAINTY_DAYS: i64 = 30;
+
+/// Public launch of Telegram, the earliest date any account can have.
+pub fn telegram_launch() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2013, 8, 14, 0, 0, 0).unwrap()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EstimateKind {
+    /// The ID is below the oldest data point.
+    OlderThan,
+    /// The ID lies between two data points and was interpolated.
+    Approx,
+    /// The ID is above the newest data point and was extrapolated with the growth model.
+    Extrapolated,
+    /// The ID is above the newest data point and no growth model could be fitted.
+    NewerThan,
+}
+
+impl fmt::Display for EstimateKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            EstimateKind::OlderThan => "older_than",
+            EstimateKind::Approx => "approx",
+            EstimateKind::Extrapolated => "extrapolated",
+            EstimateKind::NewerThan => "newer_than",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Confidence {
+    Low,
+    Medium,
+    High,
+}
+
+impl Confidence {
+    fn from_span(lower: DateTime<Utc>, upper: DateTime<Utc>) -> Self {
+        let days = (upper - lower).num_days();
+        if days < HIGH_CONFIDENCE_SPAN_DAYS {
+            Confidence::High
+        } else if days < MEDIUM_CONFIDENCE_SPAN_DAYS {
+            Confidence::Medium
+        } else {
+            Confidence::Low
+        }
+    }
+}
+
+impl fmt::Display for Confidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Confidence::Low => "low",
+            Confidence::Medium => "medium",
+            Confidence::High => "high",
+        })
+    }
+}
+
+/// Estimated creation date of an account, bracketed by the data points around its ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct AgeEstimate {
+    pub kind: EstimateKind,
+    pub date: DateTime<Utc>,
+    pub lower: DateTime<Utc>,
+    pub upper: DateTime<Utc>,
+    pub confidence: Confidence,
+}
+
+fn month_year(date: &DateTime<Utc>) -> String {
+    format!("{}/{}", date.month(), date.year())
+}
+
+impl fmt::Display for AgeEstimate {
+    /// `approx 3/2015 (2/2015 – 4/2015, high)`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} ({} – {}, {})",
+            self.kind,
+            month_year(&self.date),
+            month_year(&self.lower),
+            month_year(&self.upper),
+            self.confidence
+        )
+    }
+}
+
+fn from_millis(timestamp_ms: u64) -> DateTime<Utc> {
+    DateTime::from_timestamp_millis(timestamp_ms as i64).unwrap_or_default()
+}
+
+static INDEX: OnceLock<AgeIndex> = OnceLock::new();
+
+/// Immutable lookup table over a prepared dataset: points sorted by ID plus the
+/// growth model fitted to its tail. Lookups are a binary search.
+#[derive(Debug, Clone)]
+pub struct AgeIndex {
+    points: Vec<DataPoint>,
+    model: Option<GrowthModel>,
+}
+
+impl AgeIndex {
+    /// Builds the index. The dataset must already be prepared, i.e. sorted,
+    /// unique and monotonic, as returned by [`dataset::dataset`].
+    pub fn new(dataset: &AgeDataset) -> Self {
+        debug_assert!(dataset.validate().is_empty(), "age dataset is not prepared");
+        AgeIndex {
+            points: dataset.points.clone(),
+            model: GrowthModel::fit(&dataset.points),
+        }
+    }
+
+    pub fn estimate(&self, user_id: u64) -> AgeEstimate {
+        self.estimate_at(user_id, Utc::now())
+    }
+
+    /// Like [`AgeIndex::estimate`], as of `now`: no account is dated after it.
+    pub fn estimate_at(&self, user_id: u64, now: DateTime<Utc>) -> AgeEstimate {
+        let index = self.points.partition_point(|point| point.id < user_id);
+        
+        if let Some(point) = self.points.get(index).filter(|point| point.id == user_id) {
+            let date = from_millis(point.timestamp_ms);
+            return AgeEstimate {
+                kind: EstimateKind::Approx,
+                date,
+                lower: date,
+                upper: date,
+                confidence: Confidence::High,
+            };
+        }
+        if index == 0 {
+            let date = from_millis(self.points[0].timestamp_ms);
+            return AgeEstimate {
+                kind: EstimateKind::OlderThan,
+                date,
+                lower: telegram_launch(),
+                upper: date,
+                confidence: Confidence::Low,
+            };
+        }
+        if index == self.points.len() {
+            return self.extrapolate(user_id, now);
+        }
+        
+        let (previous, next) = (self.points[index - 1], self.points[index]);
+        let (lindex, nindex) = (issuance_index(previous.id), issuance_index(next.id));
+        let span = (nindex - lindex) as f64;
+        let id_ratio = if span > 0.0 { (issuance_index(user_id) - lindex) as f64 / span } else { 0.0 };
+        let (lage, uage) = (previous.timestamp_ms as f64, next.timestamp_ms as f64);
+        
+        let (lower, upper) = (from_millis(previous.timestamp_ms), from_millis(next.timestamp_ms));
+        AgeEstimate {
+            kind: EstimateKind::Approx,
+            date: from_millis((lage + id_ratio * (uage - lage)) as u64),
+            lower,
+            upper,
+            confidence: Confidence::from_span(lower, upper),
+        }
+    }
+
+    /// Estimates an ID above the newest data point, including IDs from the 64-bit era.
+    /// IDs in the unused range between the two eras all get the date of the jump.
+    fn extrapolate(&self, user_id: u64, now: DateTime<Utc>) -> AgeEstimate {
+        let Some(model) = self.model else {
+            let date = self.points.last().map(|point| from_millis(point.timestamp_ms)).unwrap_or_default();
+            return AgeEstimate {
+                kind: EstimateKind::NewerThan,
+                date,
+                lower: date,
+                upper: now.max(date),
+                confidence: Confidence::Low,
+            };
+        };
+        
+        // Accounts cannot be created after today, whatever the model says
+        let newest = from_millis(model.anchor().timestamp_ms);
+        let date = from_millis(model.predict(user_id).max(0.0) as u64).clamp(newest, now.max(newest));
+        let uncertainty = Duration::milliseconds(((date - newest).num_milliseconds() as f64 * EXTRAPOLATION_UNCERTAINTY) as i64)
+            .max(Duration::days(MIN_EXTRAPOLATION_UNCERTAINTY_DAYS));
+        
+        AgeEstimate {
+            kind: EstimateKind::Extrapolated,
+            date,
+            lower: (date - uncertainty).max(newest),
+            upper: (date + uncertainty).min(now.max(newest)),
+            confidence: Confidence::Low,
+        }
+    }
+}
+
+/// The index over [`dataset::dataset`], built on first use.
+pub fn age_index() -> &'static AgeIndex {
+    INDEX.get_or_init(|| AgeIndex::new(dataset::dataset()))
+}
+
+pub fn get_age_estimate(user_id: u64) -> AgeEstimate {
+    age_index().estimate(user_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use extrapolate::{EXTENDED_ID_START, LEGACY_ID_MAX};
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap()
+    }
+
+    fn estimate(user_id: u64) -> AgeEstimate {
+        AgeIndex::new(&AgeDataset::embedded()).estimate_at(user_id, now())
+    }
+
+    #[test]
+    fn ids_between_the_eras_get_the_date_of_the_jump() {
+        // Telegram skipped from 32-bit IDs straight to 5 billion, so an ID in
+        // between is dated like the last 32-bit one
+        let jump = estimate(LEGACY_ID_MAX);
+        assert_eq!(jump.to_string(), "extrapolated 1/2022 (12/2021 – 2/2022, low)");
+        for id in [LEGACY_ID_MAX + 1, 3_000_000_000, EXTENDED_ID_START - 1] {
+            assert_eq!(estimate(id), jump, "{}", id);
+        }
+        assert_eq!(estimate(EXTENDED_ID_START).to_string(), jump.to_string());
+    }
+
+    #[test]
+    fn pins_64_bit_ids() {
+        assert_eq!(estimate(5_500_000_000).to_string(), "extrapolated 7/2022 (5/2022 – 10/2022, low)");
+        assert_eq!(estimate(6_000_000_000).to_string(), "extrapolated 1/2023 (9/2022 – 5/2023, low)");
+        assert_eq!(estimate(7_000_000_000).to_string(), "extrapolated 10/2023 (4/2023 – 4/2024, low)");
+        assert_eq!(estimate(8_000_000_000).to_string(), "extrapolated 5/2024 (9/2023 – 12/2024, low)");
+    }
+
+    #[test]
+    fn extrapolated_dates_grow_with_the_id_and_widen() {
+        let ids = [2_000_000_000, LEGACY_ID_MAX, 5_500_000_000, 6_000_000_000, 7_000_000_000, 8_000_000_000];
+        for pair in ids.windows(2) {
+            let (older, newer) = (estimate(pair[0]), estimate(pair[1]));
+            assert!(older.date < newer.date, "{} is not older than {}", pair[0], pair[1]);
+            assert!(older.upper - older.lower <= newer.upper - newer.lower);
+        }
+    }
+
+    #[test]
+    fn no_estimate_is_after_now() {
+        let estimate = estimate(u64::MAX);
+        assert_eq!(estimate.kind, EstimateKind::Extrapolated);
+        assert_eq!(estimate.date, now());
+        assert_eq!(estimate.upper, now());
+    }
+}
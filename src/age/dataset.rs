@@ -0,0 +1,164 @@
+use std::{fmt, fs, io, path::Path, sync::OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use super::repair::{self, RepairMode, Violation};
+
+/// Schema version understood by this build. Files with a different version are rejected.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Environment variable pointing at a dataset file that replaces the embedded one.
+pub const AGE_DATA_ENV: &str = "AGE_DATA_PATH";
+
+/// Environment variable selecting the [`RepairMode`] applied when the dataset is loaded.
+pub const AGE_DATA_REPAIR_ENV: &str = "AGE_DATA_REPAIR";
+
+const EMBEDDED: &str = include_str!("../../data/ages.json");
+
+static DATASET: OnceLock<AgeDataset> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Provenance {
+    pub source: String,
+    pub collected: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataPoint {
+    pub id: u64,
+    pub timestamp_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgeDataset {
+    pub schema_version: u32,
+    pub provenance: Provenance,
+    pub points: Vec<DataPoint>,
+}
+
+#[derive(Debug)]
+pub enum DatasetError {
+    Io(io::Error),
+    Parse(serde_json::Error),
+    UnsupportedVersion(u32),
+    Empty,
+    Invalid(Vec<Violation>),
+    InvalidRepairMode(String),
+}
+
+impl fmt::Display for DatasetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatasetError::Io(err) => write!(f, "failed to read age dataset: {}", err),
+            DatasetError::Parse(err) => write!(f, "failed to parse age dataset: {}", err),
+            DatasetError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported age dataset schema version {} (expected {})",
+                version, SCHEMA_VERSION
+            ),
+            DatasetError::Empty => write!(f, "age dataset has no data points"),
+            DatasetError::Invalid(violations) => {
+                write!(f, "age dataset has {} invalid rows", violations.len())?;
+                for violation in violations {
+                    write!(f, "\n  {}", violation)?;
+                }
+                Ok(())
+            }
+            DatasetError::InvalidRepairMode(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for DatasetError {}
+
+impl From<io::Error> for DatasetError {
+    fn from(err: io::Error) -> Self {
+        DatasetError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for DatasetError {
+    fn from(err: serde_json::Error) -> Self {
+        DatasetError::Parse(err)
+    }
+}
+
+impl AgeDataset {
+    /// The dataset compiled into the binary from `data/ages.json`, repaired with
+    /// the default [`RepairMode`].
+    pub fn embedded() -> Self {
+        Self::from_json(EMBEDDED)
+            .and_then(|dataset| dataset.prepared(RepairMode::default()))
+            .expect("embedded age dataset is invalid")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, DatasetError> {
+        let dataset: AgeDataset = serde_json::from_str(json)?;
+        if dataset.schema_version != SCHEMA_VERSION {
+            return Err(DatasetError::UnsupportedVersion(dataset.schema_version));
+        }
+        if dataset.points.is_empty() {
+            return Err(DatasetError::Empty);
+        }
+        Ok(dataset)
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, DatasetError> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// Loads the file named by `AGE_DATA_PATH`, or the embedded dataset when it is
+    /// unset, and prepares it with the mode named by `AGE_DATA_REPAIR`.
+    pub fn from_env() -> Result<Self, DatasetError> {
+        let mode = match std::env::var(AGE_DATA_REPAIR_ENV) {
+            Ok(mode) => mode.parse().map_err(DatasetError::InvalidRepairMode)?,
+            Err(_) => RepairMode::default(),
+        };
+        let dataset = match std::env::var(AGE_DATA_ENV) {
+            Ok(path) => Self::from_path(path)?,
+            Err(_) => Self::from_json(EMBEDDED)?,
+        };
+        dataset.prepared(mode)
+    }
+
+    /// Rows that are unsorted, duplicated or go back in time.
+    pub fn validate(&self) -> Vec<Violation> {
+        repair::validate(&self.points)
+    }
+
+    pub fn repair(&mut self, mode: RepairMode) {
+        repair::repair(&mut self.points, mode);
+    }
+
+    /// Validates the dataset and either repairs it with `mode` or, for
+    /// [`RepairMode::None`], rejects it with the offending rows.
+    pub fn prepared(mut self, mode: RepairMode) -> Result<Self, DatasetError> {
+        let violations = self.validate();
+        if violations.is_empty() {
+            return Ok(self);
+        }
+        if mode == RepairMode::None {
+            return Err(DatasetError::Invalid(violations));
+        }
+
+        for violation in &violations {
+            log::debug!("age dataset {}", violation);
+        }
+        log::info!("Repairing {} invalid age dataset rows ({})", violations.len(), mode);
+        self.repair(mode);
+        Ok(self)
+    }
+}
+
+/// The dataset in use, loaded on first access. A broken override is logged and the
+/// embedded dataset is used instead so estimates keep working.
+pub fn dataset() -> &'static AgeDataset {
+    DATASET.get_or_init(|| {
+        AgeDataset::from_env().unwrap_or_else(|err| {
+            log::error!("{}, falling back to the embedded dataset", err);
+            AgeDataset::embedded()
+        })
+    })
+}
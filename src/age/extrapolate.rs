@@ -0,0 +1,74 @@
+use super::dataset::DataPoint;
+
+/// Largest ID handed out while user IDs still fit in 32 bits.
+pub const LEGACY_ID_MAX: u64 = i32::MAX as u64;
+
+/// First ID of the 64-bit era. Telegram skipped everything between
+/// [`LEGACY_ID_MAX`] and this value when it ran out of 32-bit IDs.
+pub const EXTENDED_ID_START: u64 = 5_000_000_000;
+
+/// Number of newest data points the growth model is fitted over.
+const FIT_POINTS: usize = 4;
+
+/// Maps an ID to its position in the issuance sequence, closing the gap between
+/// the 32-bit and 64-bit eras so that growth can be fitted across it.
+pub fn issuance_index(id: u64) -> u64 {
+    if id <= LEGACY_ID_MAX {
+        id
+    } else if id < EXTENDED_ID_START {
+        LEGACY_ID_MAX
+    } else {
+        id - EXTENDED_ID_START + LEGACY_ID_MAX + 1
+    }
+}
+
+/// Creation date as a logarithmic function of the issuance index, fitted over
+/// the newest data points and anchored at the newest one.
+#[derive(Debug, Clone, Copy)]
+pub struct GrowthModel {
+    anchor: DataPoint,
+    /// Milliseconds per unit of `ln(issuance_index)`.
+    slope: f64,
+}
+
+impl GrowthModel {
+    /// Fits the model to the tail of `points`, which must be sorted by ID.
+    /// Returns `None` when there are too few points or the fit does not grow.
+    pub fn fit(points: &[DataPoint]) -> Option<Self> {
+        let tail = &points[points.len().saturating_sub(FIT_POINTS)..];
+        if tail.len() < 2 {
+            return None;
+        }
+
+        let samples: Vec<(f64, f64)> = tail
+            .iter()
+            .map(|point| ((issuance_index(point.id).max(1) as f64).ln(), point.timestamp_ms as f64))
+            .collect();
+        let n = samples.len() as f64;
+        let mean_x = samples.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_y = samples.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let covariance: f64 = samples.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+        let variance: f64 = samples.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+        if variance <= 0.0 {
+            return None;
+        }
+
+        let slope = covariance / variance;
+        if !slope.is_finite() || slope <= 0.0 {
+            return None;
+        }
+        Some(GrowthModel { anchor: *tail.last()?, slope })
+    }
+
+    /// The newest data point the model extrapolates from.
+    pub fn anchor(&self) -> DataPoint {
+        self.anchor
+    }
+
+    /// Predicted creation timestamp in milliseconds for an ID past the anchor.
+    pub fn predict(&self, id: u64) -> f64 {
+        let from = (issuance_index(self.anchor.id).max(1) as f64).ln();
+        let to = (issuance_index(id).max(1) as f64).ln();
+        self.anchor.timestamp_ms as f64 + self.slope * (to - from)
+    }
+}
@@ -0,0 +1,281 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use super::dataset::DataPoint;
+
+/// How to turn an invalid dataset into one the estimator can interpolate over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RepairMode {
+    /// Reject the dataset and report the offending rows.
+    None,
+    /// Pool adjacent violators so dates never decrease as IDs grow.
+    #[default]
+    Isotonic,
+    /// Keep the largest subset of points that is already monotonic.
+    DropOutliers,
+}
+
+impl fmt::Display for RepairMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RepairMode::None => "none",
+            RepairMode::Isotonic => "isotonic",
+            RepairMode::DropOutliers => "drop_outliers",
+        })
+    }
+}
+
+impl FromStr for RepairMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" | "strict" => Ok(RepairMode::None),
+            "isotonic" => Ok(RepairMode::Isotonic),
+            "drop_outliers" | "drop" => Ok(RepairMode::DropOutliers),
+            other => Err(format!("unknown repair mode: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// The ID is smaller than the one on the previous row.
+    Unsorted,
+    /// The ID already appeared on an earlier row.
+    Duplicate,
+    /// The date is earlier than the date of a smaller ID on the previous row.
+    NonMonotonic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Violation {
+    /// Zero-based index of the offending point in the dataset file.
+    pub row: usize,
+    pub kind: ViolationKind,
+    pub point: DataPoint,
+    /// The row before, or for a duplicate the earlier row with the same ID.
+    pub previous: DataPoint,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let what = match self.kind {
+            ViolationKind::Unsorted => "is not sorted by id",
+            ViolationKind::Duplicate => "duplicates an earlier id",
+            ViolationKind::NonMonotonic => "goes back in time",
+        };
+        write!(
+            f,
+            "row {} ({} → {}) {} (previous: {} → {})",
+            self.row, self.point.id, self.point.timestamp_ms, what,
+            self.previous.id, self.previous.timestamp_ms
+        )
+    }
+}
+
+/// Checks that points are sorted by ID, unique and non-decreasing in time.
+pub fn validate(points: &[DataPoint]) -> Vec<Violation> {
+    // Duplicates are checked against every earlier row, since in an unsorted
+    // file the two rows need not be next to each other
+    let mut seen = HashMap::with_capacity(points.len());
+    let mut violations = Vec::new();
+    for (row, &point) in points.iter().enumerate() {
+        let first = *seen.entry(point.id).or_insert(row);
+        let Some(&previous) = row.checked_sub(1).and_then(|index| points.get(index)) else {
+            continue;
+        };
+        let (kind, previous) = if first != row {
+            (ViolationKind::Duplicate, points[first])
+        } else if point.id < previous.id {
+            (ViolationKind::Unsorted, previous)
+        } else if point.timestamp_ms < previous.timestamp_ms {
+            (ViolationKind::NonMonotonic, previous)
+        } else {
+            continue;
+        };
+        violations.push(Violation { row, kind, point, previous });
+    }
+    violations
+}
+
+/// Sorts and deduplicates the points, then applies `mode`. The result always
+/// passes [`validate`] unless `mode` is [`RepairMode::None`].
+pub fn repair(points: &mut Vec<DataPoint>, mode: RepairMode) {
+    if mode == RepairMode::None {
+        return;
+    }
+
+    // Duplicate IDs are merged into one point at their mean date, remembering
+    // how many observations each point stands for.
+    points.sort_by_key(|point| point.id);
+    let mut merged: Vec<(DataPoint, u64)> = Vec::with_capacity(points.len());
+    for &point in points.iter() {
+        match merged.last_mut() {
+            Some((last, weight)) if last.id == point.id => {
+                let total = last.timestamp_ms as u128 * *weight as u128 + point.timestamp_ms as u128;
+                *weight += 1;
+                last.timestamp_ms = (total / *weight as u128) as u64;
+            }
+            _ => merged.push((point, 1)),
+        }
+    }
+
+    *points = match mode {
+        RepairMode::None => unreachable!(),
+        RepairMode::Isotonic => isotonic(&merged),
+        RepairMode::DropOutliers => drop_outliers(&merged),
+    };
+}
+
+/// Pool-adjacent-violators: every run of points whose dates decrease is
+/// replaced by the weighted mean of the run.
+fn isotonic(points: &[(DataPoint, u64)]) -> Vec<DataPoint> {
+    // (sum of timestamps, total weight, number of points) per pooled block
+    let mut blocks: Vec<(f64, f64, usize)> = Vec::with_capacity(points.len());
+    for &(point, weight) in points {
+        blocks.push((point.timestamp_ms as f64 * weight as f64, weight as f64, 1));
+        while blocks.len() > 1 {
+            let (sum, weight, count) = blocks[blocks.len() - 1];
+            let (prev_sum, prev_weight, prev_count) = blocks[blocks.len() - 2];
+            if prev_sum / prev_weight <= sum / weight {
+                break;
+            }
+            blocks.pop();
+            *blocks.last_mut().unwrap() = (prev_sum + sum, prev_weight + weight, prev_count + count);
+        }
+    }
+
+    let mut repaired = Vec::with_capacity(points.len());
+    let mut ids = points.iter().map(|(point, _)| point.id);
+    for (sum, weight, count) in blocks {
+        let timestamp_ms = (sum / weight).round() as u64;
+        repaired.extend(ids.by_ref().take(count).map(|id| DataPoint { id, timestamp_ms }));
+    }
+    repaired
+}
+
+/// Keeps the longest run of points (not necessarily adjacent) whose dates
+/// never decrease, dropping everything else.
+fn drop_outliers(points: &[(DataPoint, u64)]) -> Vec<DataPoint> {
+    let n = points.len();
+    let mut length = vec![1usize; n];
+    let mut parent = vec![usize::MAX; n];
+    for i in 0..n {
+        for j in 0..i {
+            if points[j].0.timestamp_ms <= points[i].0.timestamp_ms && length[j] + 1 > length[i] {
+                length[i] = length[j] + 1;
+                parent[i] = j;
+            }
+        }
+    }
+
+    let mut kept = Vec::new();
+    let mut current = (0..n).max_by_key(|&i| length[i]);
+    while let Some(i) = current {
+        kept.push(points[i].0);
+        current = (parent[i] != usize::MAX).then_some(parent[i]);
+    }
+    kept.reverse();
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::age::dataset::{AgeDataset, DatasetError, Provenance, SCHEMA_VERSION};
+
+    fn points(rows: &[(u64, u64)]) -> Vec<DataPoint> {
+        rows.iter().map(|&(id, timestamp_ms)| DataPoint { id, timestamp_ms }).collect()
+    }
+
+    fn kinds(violations: &[Violation]) -> Vec<(usize, ViolationKind)> {
+        violations.iter().map(|violation| (violation.row, violation.kind)).collect()
+    }
+
+    #[test]
+    fn valid_points_have_no_violations() {
+        assert!(validate(&points(&[(1, 100), (2, 100), (3, 300)])).is_empty());
+    }
+
+    #[test]
+    fn reports_unsorted_rows() {
+        let violations = validate(&points(&[(1, 100), (3, 300), (2, 200)]));
+        assert_eq!(kinds(&violations), [(2, ViolationKind::Unsorted)]);
+        assert_eq!(violations[0].previous, DataPoint { id: 3, timestamp_ms: 300 });
+    }
+
+    #[test]
+    fn reports_non_monotonic_rows() {
+        let violations = validate(&points(&[(1, 100), (2, 50)]));
+        assert_eq!(kinds(&violations), [(1, ViolationKind::NonMonotonic)]);
+    }
+
+    #[test]
+    fn reports_duplicates_that_are_not_adjacent() {
+        let violations = validate(&points(&[(1, 100), (3, 300), (2, 200), (3, 50)]));
+        assert_eq!(kinds(&violations), [(2, ViolationKind::Unsorted), (3, ViolationKind::Duplicate)]);
+        assert_eq!(violations[1].previous, DataPoint { id: 3, timestamp_ms: 300 });
+    }
+
+    #[test]
+    fn reports_identical_duplicates() {
+        let violations = validate(&points(&[(1, 100), (1, 100)]));
+        assert_eq!(kinds(&violations), [(1, ViolationKind::Duplicate)]);
+    }
+
+    #[test]
+    fn repair_sorts_unsorted_points() {
+        let mut repaired = points(&[(3, 300), (1, 100), (2, 200)]);
+        repair(&mut repaired, RepairMode::Isotonic);
+        assert_eq!(repaired, points(&[(1, 100), (2, 200), (3, 300)]));
+    }
+
+    #[test]
+    fn repair_merges_duplicates_at_their_mean_date() {
+        let mut repaired = points(&[(1, 100), (2, 400), (1, 300)]);
+        repair(&mut repaired, RepairMode::Isotonic);
+        assert_eq!(repaired, points(&[(1, 200), (2, 400)]));
+    }
+
+    #[test]
+    fn isotonic_pools_runs_that_go_back_in_time() {
+        let mut repaired = points(&[(1, 100), (2, 400), (3, 200), (4, 300), (5, 500)]);
+        repair(&mut repaired, RepairMode::Isotonic);
+        assert_eq!(repaired, points(&[(1, 100), (2, 300), (3, 300), (4, 300), (5, 500)]));
+        assert!(validate(&repaired).is_empty());
+    }
+
+    #[test]
+    fn isotonic_weighs_merged_duplicates() {
+        // Two observations at 500 outweigh one at 200
+        let mut repaired = points(&[(1, 500), (1, 500), (2, 200)]);
+        repair(&mut repaired, RepairMode::Isotonic);
+        assert_eq!(repaired, points(&[(1, 400), (2, 400)]));
+    }
+
+    #[test]
+    fn drop_outliers_keeps_the_longest_monotonic_subset() {
+        let mut repaired = points(&[(1, 100), (2, 900), (3, 200), (4, 300), (5, 400)]);
+        repair(&mut repaired, RepairMode::DropOutliers);
+        assert_eq!(repaired, points(&[(1, 100), (3, 200), (4, 300), (5, 400)]));
+        assert!(validate(&repaired).is_empty());
+    }
+
+    #[test]
+    fn none_leaves_points_alone_and_rejects_the_dataset() {
+        let original = points(&[(1, 100), (3, 300), (2, 200)]);
+        let mut unchanged = original.clone();
+        repair(&mut unchanged, RepairMode::None);
+        assert_eq!(unchanged, original);
+
+        let dataset = AgeDataset {
+            schema_version: SCHEMA_VERSION,
+            provenance: Provenance { source: "test".to_string(), collected: "2024".to_string(), notes: None },
+            points: original,
+        };
+        match dataset.prepared(RepairMode::None) {
+            Err(DatasetError::Invalid(violations)) => assert_eq!(kinds(&violations), [(2, ViolationKind::Unsorted)]),
+            other => panic!("expected the violations, got {:?}", other.map(|dataset| dataset.points)),
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//! Which bot message answered which user message, so an edited message can have
+//! its answer edited in place.
+//!
+//! The log lives in memory and only keeps the most recent answers. After a
+//! restart, or on a fresh serverless instance, edits of older messages are
+//! simply not answered again.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Mutex, OnceLock},
+};
+
+use teloxide::types::{ChatId, MessageId};
+
+/// How many answers are remembered before the oldest are forgotten.
+pub const MAX_TRACKED_ANSWERS: usize = 4096;
+
+static ANSWERS: OnceLock<Mutex<AnswerLog>> = OnceLock::new();
+
+/// Bounded map from `(chat, analysed message)` to the bot's answer.
+#[derive(Debug, Default)]
+pub struct AnswerLog {
+    answers: HashMap<(ChatId, MessageId), MessageId>,
+    order: VecDeque<(ChatId, MessageId)>,
+    capacity: usize,
+}
+
+impl AnswerLog {
+    pub fn with_capacity(capacity: usize) -> Self {
+        AnswerLog { answers: HashMap::new(), order: VecDeque::new(), capacity }
+    }
+
+    pub fn insert(&mut self, chat_id: ChatId, source: MessageId, answer: MessageId) {
+        if self.answers.insert((chat_id, source), answer).is_none() {
+            self.order.push_back((chat_id, source));
+        }
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.answers.remove(&oldest);
+            }
+        }
+    }
+
+    pub fn get(&self, chat_id: ChatId, source: MessageId) -> Option<MessageId> {
+        self.answers.get(&(chat_id, source)).copied()
+    }
+}
+
+fn log() -> &'static Mutex<AnswerLog> {
+    ANSWERS.get_or_init(|| Mutex::new(AnswerLog::with_capacity(MAX_TRACKED_ANSWERS)))
+}
+
+/// Records that `answer` was sent in reply to `source`.
+pub fn remember(chat_id: ChatId, source: MessageId, answer: MessageId) {
+    log().lock().unwrap().insert(chat_id, source, answer);
+}
+
+/// The bot's answer to `source`, if it is still remembered.
+pub fn answer_to(chat_id: ChatId, source: MessageId) -> Option<MessageId> {
+    log().lock().unwrap().get(chat_id, source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHAT: ChatId = ChatId(-1001234567890);
+
+    #[test]
+    fn forgets_the_oldest_answer_over_capacity() {
+        let mut log = AnswerLog::with_capacity(2);
+        log.insert(CHAT, MessageId(1), MessageId(101));
+        log.insert(CHAT, MessageId(2), MessageId(102));
+        log.insert(CHAT, MessageId(3), MessageId(103));
+
+        assert_eq!(log.get(CHAT, MessageId(1)), None);
+        assert_eq!(log.get(CHAT, MessageId(2)), Some(MessageId(102)));
+        assert_eq!(log.get(CHAT, MessageId(3)), Some(MessageId(103)));
+        assert_eq!(log.order.len(), 2);
+    }
+
+    #[test]
+    fn answering_again_replaces_the_answer() {
+        let mut log = AnswerLog::with_capacity(2);
+        log.insert(CHAT, MessageId(1), MessageId(101));
+        log.insert(CHAT, MessageId(1), MessageId(201));
+
+        assert_eq!(log.get(CHAT, MessageId(1)), Some(MessageId(201)));
+        assert_eq!(log.order.len(), 1);
+        // The same message in another chat is a different source
+        assert_eq!(log.get(ChatId(42), MessageId(1)), None);
+    }
+}
@@ -0,0 +1,752 @@
+use teloxide::{
+    payloads::SendMessage,
+    prelude::*,
+    requests::JsonRequest,
+    types::{
+        ButtonRequest, CallbackQuery, Chat, FileId, InlineKeyboardButton, InlineKeyboardMarkup, InlineQuery,
+        InlineQueryResult, InlineQueryResultArticle, InputFile, InputMedia, InputMediaPhoto, InputMessageContent, InputMessageContentText,
+        KeyboardButton, KeyboardButtonRequestChat, KeyboardButtonRequestUsers, KeyboardMarkup, Me,
+        MessageKind, ParseMode, Recipient, ReplyParameters, RequestId, Update, UpdateKind, UserId,
+    },
+    utils::{command::BotCommands, html},
+    ApiError, RequestError,
+};
+use tokio::sync::OnceCell;
+
+use crate::{
+    answers,
+    format::{
+        chat_context_section, chat_full_section, chat_section, id_section, member_section, message_report, profile_photos_section,
+        push_profile_photos, replied_message_report, reported_users, shared_chat_section, shared_user_section, sticker_set_reports,
+        user_section, MAX_PROFILE_PHOTOS,
+    },
+    group::{group_mode, is_addressed_to, set_group_mode, GroupMode},
+    render::{truncated_plain, OutputFormat},
+    report::{Field, Report, Section, Value},
+    tree::{Node, Tree},
+    settings::{self, Settings, SettingsChange, SettingsKey, Verbosity},
+};
+
+#[derive(BotCommands, Clone, Debug)]
+#[command(rename_rule = "lowercase", description = "These commands are supported:")]
+pub enum Command {
+    #[command(description = "Display help text")]
+    Help,
+    #[command(description = "Start the bot")]
+    Start,
+    #[command(description = "Analyse a user, group or channel ID, e.g. /id 123456789, or reply with /id to analyse a message")]
+    Id(String),
+    #[command(description = "Show the analysis as JSON: send it alone, with an ID, or as a reply")]
+    Json(String),
+    #[command(description = "List the file_id of every sticker in a set, e.g. /stickerset <name>, or reply to a sticker")]
+    StickerSet(String),
+    #[command(description = "Show and send a user's profile photos: /photos for yours, /photos <user id>, or reply with /photos")]
+    Photos(String),
+    #[command(description = "Show the ID of this chat, its topic and its linked chat")]
+    ChatId,
+    #[command(description = "Show everything Telegram tells me about this chat, or /chat <id or @username>")]
+    Chat(String),
+    #[command(description = "Show a member's status, title, rights and restrictions in this group: reply with /whois, or /whois <user id>")]
+    Whois(String),
+    #[command(description = "Set which messages I answer in this group (admins only)")]
+    GroupMode(String),
+    #[command(description = "Show buttons to pick a user, bot, group or channel")]
+    Pick,
+    #[command(description = "Change your preferences, or the group's as an admin")]
+    Settings,
+}
+
+static ME: OnceCell<Me> = OnceCell::const_new();
+
+/// The bot's own account, fetched once per process.
+pub async fn me(bot: &Bot) -> ResponseResult<&'static Me> {
+    ME.get_or_try_init(|| async { bot.get_me().await }).await
+}
+
+/// Whether the sender may change settings of the chat: an administrator, the
+/// owner, or an anonymous admin posting on behalf of the chat itself.
+async fn is_chat_admin(bot: &Bot, msg: &Message) -> ResponseResult<bool> {
+    if msg.sender_chat.as_ref().is_some_and(|chat| chat.id == msg.chat.id) {
+        return Ok(true);
+    }
+    match &msg.from {
+        Some(user) => is_admin(bot, &msg.chat, user.id).await,
+        None => Ok(false),
+    }
+}
+
+async fn is_admin(bot: &Bot, chat: &Chat, user_id: UserId) -> ResponseResult<bool> {
+    if chat.is_private() {
+        return Ok(true);
+    }
+    Ok(bot.get_chat_member(chat.id, user_id).await?.is_privileged())
+}
+
+/// Prefix of the callback data sent by the `/settings` keyboard, followed by
+/// `<field>:<value>`.
+const SETTINGS_CALLBACK: &str = "settings";
+
+/// Private chats edit the user's own settings, groups edit the group's.
+fn settings_key(chat: &Chat, user_id: UserId) -> SettingsKey {
+    if chat.is_private() {
+        SettingsKey::User(user_id)
+    } else {
+        SettingsKey::Chat(chat.id)
+    }
+}
+
+fn settings_text(key: SettingsKey, settings: &Settings) -> String {
+    match key {
+        SettingsKey::User(_) => {
+            let tree = Tree::new("⚙️ Your settings")
+                .child(Node::new(format!("verbosity: {}", settings.verbosity)))
+                .child(Node::new(format!("format: {}", settings.format)));
+            format!("{}\nTap a button to change them.", tree)
+        }
+        SettingsKey::Chat(_) => {
+            let tree = Tree::new("⚙️ Group settings").child(Node::new(format!(
+                "group_mode: {} ({})",
+                settings.group_mode,
+                settings.group_mode.description()
+            )));
+            format!("{}\nAdmins can tap a button to change it.", tree)
+        }
+    }
+}
+
+fn settings_keyboard(key: SettingsKey, settings: &Settings) -> InlineKeyboardMarkup {
+    let button = |field: &str, value: String, selected: bool| {
+        let label = if selected { format!("✅ {}", value) } else { value.clone() };
+        InlineKeyboardButton::callback(label, format!("{}:{}:{}", SETTINGS_CALLBACK, field, value))
+    };
+    
+    let rows = match key {
+        SettingsKey::User(_) => vec![
+            Verbosity::ALL
+                .iter()
+                .map(|&verbosity| button("verbosity", verbosity.to_string(), verbosity == settings.verbosity))
+                .collect::<Vec<_>>(),
+            OutputFormat::ALL
+                .iter()
+                .map(|&format| button("format", format.to_string(), format == settings.format))
+                .collect(),
+        ],
+        SettingsKey::Chat(_) => vec![GroupMode::ALL
+            .iter()
+            .map(|&mode| button("group_mode", mode.to_string(), mode == settings.group_mode))
+            .collect()],
+    };
+    InlineKeyboardMarkup::new(rows)
+}
+
+/// Applies a `settings:<field>:<value>` callback and returns the new settings.
+async fn apply_setting(key: SettingsKey, field: &str, value: &str) -> Result<Settings, String> {
+    let change: SettingsChange = match (key, field) {
+        (SettingsKey::User(_), "verbosity") => {
+            let verbosity = value.parse::<Verbosity>()?;
+            Box::new(move |settings| settings.verbosity = verbosity)
+        }
+        (SettingsKey::User(_), "format") => {
+            let format = value.parse::<OutputFormat>()?;
+            Box::new(move |settings| settings.format = format)
+        }
+        (SettingsKey::Chat(_), "group_mode") => {
+            let mode = value.parse::<GroupMode>()?;
+            Box::new(move |settings| settings.group_mode = mode)
+        }
+        _ => return Err(format!("unknown setting: {}", field)),
+    };
+    settings::update(key, change).await.map_err(|err| err.to_string())
+}
+
+pub async fn callback_query_handler(bot: Bot, query: CallbackQuery) -> ResponseResult<()> {
+    let (Some(data), Some(message)) = (query.data.as_deref(), query.regular_message()) else {
+        bot.answer_callback_query(query.id).await?;
+        return Ok(());
+    };
+    let Some((field, value)) = data
+        .strip_prefix(SETTINGS_CALLBACK)
+        .and_then(|rest| rest.strip_prefix(':'))
+        .and_then(|rest| rest.split_once(':'))
+    else {
+        bot.answer_callback_query(query.id).await?;
+        return Ok(());
+    };
+    
+    let key = settings_key(&message.chat, query.from.id);
+    if !is_admin(&bot, &message.chat, query.from.id).await? {
+        bot.answer_callback_query(query.id)
+            .text("Only group admins can change these settings.")
+            .show_alert(true)
+            .await?;
+        return Ok(());
+    }
+    
+    match apply_setting(key, field, value).await {
+        Ok(settings) => {
+            bot.edit_message_text(message.chat.id, message.id, settings_text(key, &settings))
+                .reply_markup(settings_keyboard(key, &settings))
+                .await?;
+            bot.answer_callback_query(query.id).text("Saved").await?;
+        }
+        Err(err) => {
+            log::error!("Failed to change {} for {}: {}", field, key, err);
+            bot.answer_callback_query(query.id)
+                .text("Could not save this setting, please try again later.")
+                .show_alert(true)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+fn group_mode_help(current: GroupMode) -> String {
+    let mut text = format!("Group mode: {} ({})\n\nAdmins can change it with:\n", current, current.description());
+    for mode in GroupMode::ALL {
+        text.push_str(&format!("/groupmode {} - {}\n", mode, mode.description()));
+    }
+    text
+}
+
+/// Request IDs of the picker buttons, echoed back in `users_shared`/`chat_shared`.
+const PICK_USER: RequestId = RequestId(1);
+const PICK_BOT: RequestId = RequestId(2);
+const PICK_GROUP: RequestId = RequestId(3);
+const PICK_CHANNEL: RequestId = RequestId(4);
+
+/// Most users Telegram lets a single request_users button share at once.
+const MAX_PICKED_USERS: u8 = 10;
+
+/// Reply keyboard whose buttons open Telegram's native user and chat pickers,
+/// so IDs can be looked up without forwarding anything.
+pub fn picker_keyboard() -> KeyboardMarkup {
+    let users = |text: &str, request_id: RequestId, is_bot: bool| {
+        KeyboardButton::new(text).request(ButtonRequest::RequestUsers(
+            KeyboardButtonRequestUsers::new(request_id)
+                .user_is_bot(is_bot)
+                .max_quantity(MAX_PICKED_USERS)
+                .request_name()
+                .request_username(),
+        ))
+    };
+    let chat = |text: &str, request_id: RequestId, is_channel: bool| {
+        KeyboardButton::new(text).request(ButtonRequest::RequestChat(
+            KeyboardButtonRequestChat::new(request_id, is_channel)
+                .request_title()
+                .request_username(),
+        ))
+    };
+    
+    KeyboardMarkup::new(vec![
+        vec![users("👤 Pick a user", PICK_USER, false), users("🤖 Pick a bot", PICK_BOT, true)],
+        vec![chat("👥 Pick a group", PICK_GROUP, false), chat("📢 Pick a channel", PICK_CHANNEL, true)],
+    ])
+    .resize_keyboard()
+    .persistent()
+}
+
+const ID_USAGE: &str = "Usage: /id <number>, or reply to a message with /id\n\nExamples:\n/id 123456789 - a user or bot\n/id -1001234567890 - a supergroup or channel\n/id -123456789 - a basic group";
+
+const STICKER_SET_USAGE: &str = "Usage: /stickerset <name or t.me/addstickers link>, or reply to a sticker with /stickerset";
+
+const JSON_USAGE: &str = "Usage: /json, /json <number>, or reply to a message with /json";
+
+const PHOTOS_USAGE: &str = "Usage: /photos for your own, /photos <user id>, or reply to a message with /photos";
+
+const WHOIS_USAGE: &str = "Usage: /whois to check yourself, /whois <user id>, or reply to a message with /whois";
+
+/// What `/id` and `/json` analyse: the ID given as argument, or the message
+/// replied to when there is no argument.
+fn command_report(msg: &Message, arg: &str, me: &Me) -> Option<Report> {
+    match (arg.trim().parse::<i64>(), msg.reply_to_message()) {
+        (Ok(id), _) => Some(id_section(id).into()),
+        (Err(_), Some(reply)) if arg.trim().is_empty() => Some(replied_message_report(reply, Some(me))),
+        _ => None,
+    }
+}
+
+/// The user a command like `/whois` or `/photos` is about: the ID given as
+/// argument, else the sender of the message replied to, else the sender.
+/// `None` if the argument is not a user ID.
+fn command_user(msg: &Message, arg: &str) -> Option<UserId> {
+    let arg = arg.trim();
+    match arg.parse::<u64>() {
+        Ok(id) => Some(UserId(id)),
+        Err(_) if arg.is_empty() => msg.reply_to_message().unwrap_or(msg).from.as_ref().map(|user| user.id),
+        Err(_) => None,
+    }
+}
+
+/// Adds the profile photo count of every user in `report`. A failed lookup
+/// only leaves the count out.
+async fn count_profile_photos(bot: &Bot, report: &mut Report) {
+    for user_id in reported_users(report) {
+        match bot.get_user_profile_photos(user_id).limit(1).await {
+            Ok(photos) => push_profile_photos(report, user_id, photos.total_count),
+            Err(err) => log::warn!("getUserProfilePhotos failed for {}: {:?}", user_id, err),
+        }
+    }
+}
+
+/// The settings of whoever sent `msg`, with the group mode of its chat.
+async fn sender_settings(msg: &Message) -> Settings {
+    settings::effective(msg.chat.id, msg.from.as_ref().map(|user| user.id)).await
+}
+
+pub async fn answer(bot: Bot, msg: Message, cmd: Command) -> ResponseResult<()> {
+    match cmd {
+        Command::Help => {
+            reply(&bot, &msg, Command::descriptions().to_string()).await?;
+        }
+        Command::Start => {
+            let first_name = msg.from.as_ref().map(|user| user.first_name.as_str()).unwrap_or("there");
+            let bot_info = me(&bot).await?;
+            
+            let mut welcome_text = format!("Hi {}!\n\n", first_name);
+            welcome_text.push_str(&format!("🤖 Telegram ID Bot (ID: {})\n\n", bot_info.id));
+            welcome_text.push_str("How this bot works:\n");
+            welcome_text.push_str("• Send me any message to see your detailed user information\n");
+            welcome_text.push_str("• Forward any message to me to see both your info and the original sender's details\n");
+            welcome_text.push_str("• I can estimate account creation dates based on user IDs\n");
+            welcome_text.push_str("• Send a photo, file or sticker to get its file_id\n");
+            welcome_text.push_str("• Send /id <number> or just a number to analyse any user, group or channel ID\n");
+            welcome_text.push_str(&format!("• Type @{} in any chat to share your IDs without opening this chat\n", bot_info.username()));
+            welcome_text.push_str("• Use /pick to look up a user, bot, group or channel without forwarding from it\n");
+            welcome_text.push_str("• All information is displayed in a clean tree format; use /settings for JSON, YAML or a compact list of IDs\n\n");
+            welcome_text.push_str("Try sending me a message or forwarding one to see it in action!");
+            
+            // Request buttons only work in private chats
+            let mut request = reply(&bot, &msg, welcome_text);
+            if msg.chat.is_private() {
+                request = request.reply_markup(picker_keyboard());
+            }
+            request.await?;
+        }
+        Command::Id(arg) => {
+            let text = match command_report(&msg, &arg, me(&bot).await?) {
+                Some(mut report) => {
+                    count_profile_photos(&bot, &mut report).await;
+                    sender_settings(&msg).await.format.render(&report)
+                }
+                None => html::escape(ID_USAGE),
+            };
+            answer_html(&bot, &msg, text).await?;
+        }
+        Command::Json(arg) => {
+            let me = me(&bot).await?;
+            let report = match command_report(&msg, &arg, me) {
+                Some(report) => Some(report),
+                None if arg.trim().is_empty() => Some(message_report(&msg, sender_settings(&msg).await.verbosity, Some(me))),
+                None => None,
+            };
+            let text = match report {
+                Some(mut report) => {
+                    count_profile_photos(&bot, &mut report).await;
+                    OutputFormat::Json.render(&report)
+                }
+                None => html::escape(JSON_USAGE),
+            };
+            answer_html(&bot, &msg, text).await?;
+        }
+        Command::StickerSet(arg) => {
+            let from_reply = msg.reply_to_message().and_then(|reply| reply.sticker()).and_then(|sticker| sticker.set_name.clone());
+            let arg = arg.trim();
+            let name = match arg.rsplit_once("/addstickers/") {
+                Some((_, name)) => name,
+                None if arg.is_empty() => from_reply.as_deref().unwrap_or_default(),
+                None => arg,
+            };
+            if name.is_empty() {
+                reply(&bot, &msg, STICKER_SET_USAGE).await?;
+                return Ok(());
+            }
+
+            match bot.get_sticker_set(name).await {
+                Ok(set) => {
+                    // Pages are not remembered as answers, so editing the command does not resend them
+                    let format = sender_settings(&msg).await.format;
+                    for report in sticker_set_reports(&set, format) {
+                        send_html(&bot, &msg, format.render(&report)).await?;
+                    }
+                }
+                Err(RequestError::Api(ApiError::InvalidStickersSet)) => {
+                    reply(&bot, &msg, format!("There is no sticker set named {}.", name)).await?;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Command::Photos(arg) => {
+            let Some(user_id) = command_user(&msg, &arg) else {
+                reply(&bot, &msg, PHOTOS_USAGE).await?;
+                return Ok(());
+            };
+
+            match bot.get_user_profile_photos(user_id).limit(MAX_PROFILE_PHOTOS).await {
+                Ok(photos) => {
+                    let report = profile_photos_section(user_id, &photos).into();
+                    answer_html(&bot, &msg, sender_settings(&msg).await.format.render(&report)).await?;
+                    // Editing the command updates the list but does not send the photos again
+                    if msg.edit_date().is_none() {
+                        let largest = photos.photos.iter().filter_map(|sizes| sizes.last()).map(|size| size.file.id.clone());
+                        send_photos(&bot, &msg, largest.collect()).await?;
+                    }
+                }
+                Err(RequestError::Api(err)) => {
+                    reply(&bot, &msg, format!("Could not get the profile photos of {}: {}", user_id, err)).await?;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Command::ChatId => {
+            // The linked chat is only part of the full chat info
+            let linked_chat_id = match bot.get_chat(msg.chat.id).await {
+                Ok(full_info) => full_info.linked_chat_id(),
+                Err(err) => {
+                    log::warn!("getChat failed for {}: {:?}", msg.chat.id, err);
+                    None
+                }
+            };
+            
+            let report = Report::from_iter([
+                chat_section(&msg.chat),
+                chat_context_section(msg.thread_id.filter(|_| msg.is_topic_message), linked_chat_id),
+            ]);
+            answer_html(&bot, &msg, sender_settings(&msg).await.format.render(&report)).await?;
+        }
+        Command::Chat(arg) => {
+            let arg = arg.trim();
+            let recipient = if arg.is_empty() {
+                Recipient::Id(msg.chat.id)
+            } else if let Ok(id) = arg.parse::<i64>() {
+                Recipient::Id(ChatId(id))
+            } else {
+                Recipient::ChannelUsername(format!("@{}", arg.trim_start_matches('@')))
+            };
+
+            let section = match bot.get_chat(recipient).await {
+                Ok(info) => chat_full_section(&info),
+                // Unknown chats, chats the bot was removed from and users who never
+                // talked to the bot: show what is known without getChat
+                Err(RequestError::Api(err)) => {
+                    let section = match arg.parse::<i64>() {
+                        _ if arg.is_empty() => chat_section(&msg.chat),
+                        Ok(id) => id_section(id),
+                        Err(_) => Section::new("chat_full", "💬", "Chat details")
+                            .field(Field::new("username", Value::Code(arg.trim_start_matches('@').to_string()))),
+                    };
+                    section.field(Field::new("full_info", Value::None).note(format!("getChat failed: {}", err)))
+                }
+                Err(err) => return Err(err),
+            };
+            answer_html(&bot, &msg, sender_settings(&msg).await.format.render(&section.into())).await?;
+        }
+        Command::Whois(arg) => {
+            if msg.chat.is_private() {
+                reply(&bot, &msg, "/whois only works in groups, where members have a status.").await?;
+                return Ok(());
+            }
+            let Some(user_id) = command_user(&msg, &arg) else {
+                reply(&bot, &msg, WHOIS_USAGE).await?;
+                return Ok(());
+            };
+
+            let report = match bot.get_chat_member(msg.chat.id, user_id).await {
+                Ok(member) => Report::from_iter([user_section(&member.user, "user", "User", Some(me(&bot).await?)), member_section(&member)]),
+                // Users Telegram does not know in this chat, or a bot that may not look
+                Err(RequestError::Api(err)) => id_section(user_id.0 as i64)
+                    .field(Field::new("member", Value::None).note(format!("getChatMember failed: {}", err)))
+                    .into(),
+                Err(err) => return Err(err),
+            };
+            answer_html(&bot, &msg, sender_settings(&msg).await.format.render(&report)).await?;
+        }
+        Command::GroupMode(arg) => {
+            let text = if msg.chat.is_private() {
+                "Group mode only applies to groups. In private chats I answer every message.".to_string()
+            } else if arg.trim().is_empty() {
+                group_mode_help(group_mode(msg.chat.id).await)
+            } else if !is_chat_admin(&bot, &msg).await? {
+                "Only group admins can change the group mode.".to_string()
+            } else {
+                match arg.parse::<GroupMode>() {
+                    Ok(mode) => match set_group_mode(msg.chat.id, mode).await {
+                        Ok(()) => format!("Group mode set to {} ({}).", mode, mode.description()),
+                        Err(err) => {
+                            log::error!("Failed to save group mode for {}: {}", msg.chat.id, err);
+                            "Could not save the group mode, please try again later.".to_string()
+                        }
+                    },
+                    Err(err) => format!("{}\n\n{}", err, group_mode_help(group_mode(msg.chat.id).await)),
+                }
+            };
+            reply(&bot, &msg, text).await?;
+        }
+        Command::Settings => {
+            let Some(user) = &msg.from else {
+                return Ok(());
+            };
+            let key = settings_key(&msg.chat, user.id);
+            let settings = settings::load(key).await;
+            reply(&bot, &msg, settings_text(key, &settings))
+                .reply_markup(settings_keyboard(key, &settings))
+                .await?;
+        }
+        Command::Pick => {
+            if msg.chat.is_private() {
+                reply(&bot, &msg, "Use the buttons below to pick a user, bot, group or channel.")
+                    .reply_markup(picker_keyboard())
+                    .await?;
+            } else {
+                reply(&bot, &msg, "The picker only works in a private chat with me.").await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn message_handler(bot: Bot, msg: Message) -> ResponseResult<()> {
+    let settings = sender_settings(&msg).await;
+    let me = me(&bot).await?;
+    let mut report = match &msg.kind {
+        // Answers to the picker buttons
+        MessageKind::UsersShared(shared) => {
+            let is_bot = shared.users_shared.request_id == PICK_BOT;
+            shared.users_shared.users
+                .iter()
+                .map(|user| shared_user_section(user, is_bot, Some(me)))
+                .collect()
+        }
+        MessageKind::ChatShared(shared) => {
+            shared_chat_section(&shared.chat_shared, shared.chat_shared.request_id == PICK_CHANNEL).into()
+        }
+        // A message that is just a number is treated like /id <number>
+        _ => match msg.text().and_then(|text| text.trim().parse::<i64>().ok()) {
+            Some(id) => id_section(id).into(),
+            None => {
+                // Mentioning the bot in reply to someone else analyses that message; other
+                // replies, such as those seen in `GroupMode::All`, describe the message itself
+                match msg.reply_to_message() {
+                    Some(reply)
+                        if !msg.chat.is_private()
+                            && reply.from.as_ref().map(|user| user.id) != Some(me.id)
+                            && is_addressed_to(&msg, me) =>
+                    {
+                        replied_message_report(reply, Some(me))
+                    }
+                    _ => message_report(&msg, settings.verbosity, Some(me)),
+                }
+            }
+        },
+    };
+    
+    count_profile_photos(&bot, &mut report).await;
+    answer_html(&bot, &msg, settings.format.render(&report)).await?;
+
+    Ok(())
+}
+
+/// A message answering `msg`: sent as a reply to it and, in forums, into the
+/// same topic.
+fn reply(bot: &Bot, msg: &Message, text: impl Into<String>) -> JsonRequest<SendMessage> {
+    let mut request = bot
+        .send_message(msg.chat.id, text)
+        .reply_parameters(ReplyParameters::new(msg.id).allow_sending_without_reply());
+    if let Some(thread_id) = msg.thread_id.filter(|_| msg.is_topic_message) {
+        request = request.message_thread_id(thread_id);
+    }
+    request
+}
+
+/// Sends an analysis as HTML in reply to `msg`, or edits the earlier answer if
+/// `msg` is an edited message. Should Telegram reject the markup, the same text
+/// is sent again without formatting rather than not answering at all.
+async fn answer_html(bot: &Bot, msg: &Message, text: String) -> ResponseResult<()> {
+    if let Some(answer_id) = msg.edit_date().and_then(|_| answers::answer_to(msg.chat.id, msg.id)) {
+        let edited = match bot.edit_message_text(msg.chat.id, answer_id, text.clone()).parse_mode(ParseMode::Html).await {
+            Err(RequestError::Api(ApiError::CantParseEntities(err))) => {
+                log::warn!("Editing as plain text, Telegram could not parse the HTML: {}", err);
+                bot.edit_message_text(msg.chat.id, answer_id, truncated_plain(&text)).await
+            }
+            Err(RequestError::Api(ApiError::EditedMessageIsTooLong)) => {
+                log::warn!("Editing as truncated plain text, the answer is too long");
+                bot.edit_message_text(msg.chat.id, answer_id, truncated_plain(&text)).await
+            }
+            result => result,
+        };
+        match edited {
+            // An edit that does not change the analysis, e.g. a fixed typo
+            Ok(_) | Err(RequestError::Api(ApiError::MessageNotModified)) => return Ok(()),
+            // The answer was deleted, so send a new one below
+            Err(RequestError::Api(ApiError::MessageToEditNotFound)) => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    let sent = send_html(bot, msg, text).await?;
+    answers::remember(msg.chat.id, msg.id, sent.id);
+    Ok(())
+}
+
+/// Replies to `msg` with HTML, falling back to plain text like [`answer_html`]
+/// but without remembering the answer. Text that is still too long for one
+/// message is cut, so there is always an answer.
+async fn send_html(bot: &Bot, msg: &Message, text: String) -> ResponseResult<Message> {
+    match reply(bot, msg, text.clone()).parse_mode(ParseMode::Html).await {
+        Err(RequestError::Api(ApiError::CantParseEntities(err))) => {
+            log::warn!("Sending as plain text, Telegram could not parse the HTML: {}", err);
+            reply(bot, msg, truncated_plain(&text)).await
+        }
+        Err(RequestError::Api(ApiError::MessageIsTooLong)) => {
+            log::warn!("Sending as truncated plain text, the answer is too long");
+            reply(bot, msg, truncated_plain(&text)).await
+        }
+        result => result,
+    }
+}
+
+/// Sends photos in reply to `msg`, as an album when there are several.
+async fn send_photos(bot: &Bot, msg: &Message, file_ids: Vec<FileId>) -> ResponseResult<()> {
+    let thread_id = msg.thread_id.filter(|_| msg.is_topic_message);
+    let reply_parameters = ReplyParameters::new(msg.id).allow_sending_without_reply();
+    match file_ids.as_slice() {
+        [] => {}
+        // An album needs at least two items
+        [file_id] => {
+            let mut request = bot.send_photo(msg.chat.id, InputFile::file_id(file_id.clone())).reply_parameters(reply_parameters);
+            if let Some(thread_id) = thread_id {
+                request = request.message_thread_id(thread_id);
+            }
+            request.await?;
+        }
+        _ => {
+            let media = file_ids.into_iter().map(|file_id| InputMedia::Photo(InputMediaPhoto::new(InputFile::file_id(file_id))));
+            let mut request = bot.send_media_group(msg.chat.id, media).reply_parameters(reply_parameters);
+            if let Some(thread_id) = thread_id {
+                request = request.message_thread_id(thread_id);
+            }
+            request.await?;
+        }
+    }
+    Ok(())
+}
+
+fn inline_article(id: impl Into<String>, title: impl Into<String>, description: impl Into<String>, text: String) -> InlineQueryResult {
+    InlineQueryResult::Article(
+        InlineQueryResultArticle::new(id, title, InputMessageContent::Text(InputMessageContentText::new(text).parse_mode(ParseMode::Html)))
+            .description(description),
+    )
+}
+    
+/// Answers `@bot <query>` in any chat with cards that insert the sender's IDs,
+/// or the analysis of the ID typed as the query.
+pub async fn inline_query_handler(bot: Bot, query: InlineQuery) -> ResponseResult<()> {
+    let format = settings::load(SettingsKey::User(query.from.id)).await.format;
+    let mut results = Vec::new();
+
+    if let Ok(id) = query.query.trim().parse::<i64>() {
+        results.push(inline_article(
+            format!("id:{}", id),
+            format!("Estimate age of {}", id),
+            "Peer type, MTProto ID and account creation estimate",
+            format.render(&id_section(id).into()),
+        ));
+    }
+    results.push(inline_article(
+        "my_id",
+        "My user ID",
+        query.from.id.to_string(),
+        html::code_inline(&query.from.id.to_string()),
+    ));
+    results.push(inline_article(
+        "my_info",
+        "My full info",
+        "Your user info tree with the account creation estimate",
+        format.render(&user_section(&query.from, "user", "User", Some(me(&bot).await?)).into()),
+    ));
+    
+    // Results depend on who is asking, so they must not be shared or cached
+    bot.answer_inline_query(query.id, results)
+        .is_personal(true)
+        .cache_time(0)
+        .await?;
+    
+    Ok(())
+}
+
+/// Answers a new or edited message: commands first, then the analysis if the
+/// chat's group mode allows it.
+async fn handle_message(bot: Bot, message: Message) {
+    log::info!("Processing message from user: {:?}", message.from.as_ref().map(|u| u.id));
+    
+    let me = match me(&bot).await {
+        Ok(me) => me,
+        Err(err) => {
+            log::error!("getMe failed: {:?}", err);
+            return;
+        }
+    };
+    
+    // Check if it's a command first
+    if let Some(text) = message.text() {
+        if text.starts_with('/') {
+            if let Ok(command) = Command::parse(text, me.username()) {
+                log::info!("Processing command: {:?}", command);
+                if let Err(err) = answer(bot.clone(), message.clone(), command).await {
+                    log::error!("Command handler error: {:?}", err);
+                }
+                return;
+            }
+        }
+    }
+    
+    // In groups, stay quiet unless the chat's mode asks for more
+    if !message.chat.is_private() {
+        match group_mode(message.chat.id).await {
+            GroupMode::Commands => return,
+            GroupMode::Mentions if !is_addressed_to(&message, me) => return,
+            GroupMode::Mentions | GroupMode::All => {}
+        }
+    }
+
+    // Handle all other messages (including forwards, photos, etc.)
+    log::info!("Processing regular message (text: {}, forward: {})", 
+              message.text().is_some(), 
+              message.forward_origin().is_some());
+    
+    if let Err(err) = message_handler(bot.clone(), message).await {
+        log::error!("Message handler error: {:?}", err);
+    }
+}
+
+/// Entry point shared by the standalone server and the Vercel function.
+pub async fn handle_update(bot: Bot, update: Update) {
+    log::info!("Received update: {:?}", update.id);
+    match update.kind {
+        UpdateKind::Message(message) => handle_message(bot, message).await,
+        UpdateKind::EditedMessage(message) => {
+            // Only edits of answered messages are analysed again, to update that answer
+            if answers::answer_to(message.chat.id, message.id).is_some() {
+                log::info!("Processing edited message {} in {}", message.id, message.chat.id);
+                handle_message(bot, message).await;
+            }
+        }
+        UpdateKind::CallbackQuery(query) => {
+            log::info!("Processing callback query from user: {:?}", query.from.id);
+            if let Err(err) = callback_query_handler(bot.clone(), query).await {
+                log::error!("Callback query handler error: {:?}", err);
+            }
+        }
+        UpdateKind::InlineQuery(query) => {
+            log::info!("Processing inline query from user: {:?}", query.from.id);
+            if let Err(err) = inline_query_handler(bot.clone(), query).await {
+                log::error!("Inline query handler error: {:?}", err);
+            }
+        }
+        _ => {
+            // Ignore other update types for now
+            log::debug!("Received non-message update: {:?}", update.kind);
+        }
+    }
+}
@@ -0,0 +1,1039 @@
+use chrono::{Duration, Utc};
+use teloxide::types::{
+    Administrator, Chat, ChatFullInfo, ChatFullInfoKind, ChatFullInfoPublicKind, ChatFullInfoPublicSupergroup,
+    ChatKind, ChatMember, ChatMemberKind, ChatPermissions, ChatPublic, ChatShared, FileMeta, MaskPoint, Me, Message, MessageEntity, MessageEntityKind, MessageEntityRef, MessageOrigin, PhotoSize,
+    PublicChatKind, Restricted, Seconds, SharedUser, Sticker, StickerFormat, StickerKind, StickerSet, ThreadId, UntilDate, User, UserId, UserProfilePhotos,
+};
+
+use crate::{
+    age::get_age_estimate,
+    peer::{PeerId, PeerKind},
+    render::{message_length, OutputFormat, MAX_MESSAGE_LENGTH},
+    report::{Field, Report, Section, Value},
+    settings::Verbosity,
+};
+
+// Everything below builds format-independent sections; `crate::render` decides
+// how they look.
+
+pub fn chat_type(chat: &Chat) -> &'static str {
+    match &chat.kind {
+        ChatKind::Private(_) => "private",
+        ChatKind::Public(public_chat) => {
+            match public_chat.kind {
+                PublicChatKind::Group => "group",
+                PublicChatKind::Supergroup(_) => "supergroup",
+                PublicChatKind::Channel(_) => "channel",
+            }
+        }
+    }
+}
+
+/// A user's account. `me` is the bot itself, which is the only bot whose
+/// capabilities are known.
+pub fn user_section(user: &User, key: &'static str, title: &str, me: Option<&Me>) -> Section {
+    let mut section = Section::new(key, "👤", title)
+        .field(Field::new("id", Value::Id(user.id.0 as i64)))
+        .field(Field::new("is_bot", Value::Bool(user.is_bot)));
+
+    // The name links to the account, even when it has no username
+    section.push(Field::new("first_name", Value::Mention { user_id: user.id, name: user.first_name.clone() }));
+
+    if let Some(last_name) = &user.last_name {
+        section.push(Field::new("last_name", Value::Text(last_name.clone())));
+    }
+
+    if let Some(username) = &user.username {
+        section.push(Field::new("username", Value::Code(username.clone())));
+    }
+
+    if let Some(language_code) = &user.language_code {
+        section.push(Field::new("language_code", Value::Text(language_code.clone())).note("-"));
+    }
+
+    section.push(Field::new("is_premium", Value::Bool(user.is_premium)));
+    section.push(Field::new("added_to_attachment_menu", Value::Bool(user.added_to_attachment_menu)));
+    if user.is_bot {
+        section.push(bot_capabilities(user, me));
+    }
+
+    section.field(Field::new("created", Value::Age(get_age_estimate(user.id.0))))
+}
+
+/// What a bot can do. Telegram only tells a bot about itself, through getMe,
+/// so for any other bot the flags are unknown.
+fn bot_capabilities(user: &User, me: Option<&Me>) -> Field {
+    let Some(me) = me.filter(|me| me.id == user.id) else {
+        return Field::new("bot_capabilities", Value::None).note("only known to the bot itself");
+    };
+    Field::new(
+        "bot_capabilities",
+        Value::Fields(vec![
+            Field::new("can_join_groups", Value::Bool(me.can_join_groups)),
+            Field::new("can_read_all_group_messages", Value::Bool(me.can_read_all_group_messages)),
+            Field::new("supports_inline_queries", Value::Bool(me.supports_inline_queries)),
+            Field::new("can_connect_to_business", Value::Bool(me.can_connect_to_business)),
+            Field::new("has_main_web_app", Value::Bool(me.has_main_web_app)),
+        ]),
+    )
+}
+
+/// A chat acting as a sender: a forward origin, or a channel or anonymous admin
+/// posting on behalf of a chat.
+fn sender_chat_section(chat: &Chat, key: &'static str, title: &str, origin: Option<&str>) -> Section {
+    let mut section = Section::new(key, "👤", title);
+    if let Some(origin) = origin {
+        section.push(Field::new("origin", Value::Text(origin.to_string())));
+    }
+    section.push(Field::new("chat", Value::Text(chat.title().unwrap_or("Unknown").to_string())));
+    section.push(Field::new("type", Value::Text(chat_type(chat).to_string())));
+    if let Some(username) = chat.username() {
+        section.push(Field::new("username", Value::Code(username.to_string())));
+    }
+    section.push(Field::new("id", Value::Id(chat.id.0)));
+    if let Some(peer) = PeerId::from_bot_api(chat.id.0) {
+        section.push(Field::new("mtproto_id", Value::Peer(peer)));
+    }
+    section
+}
+
+pub fn forwarded_from_section(origin: &MessageOrigin, me: Option<&Me>) -> Section {
+    match origin {
+        MessageOrigin::User { sender_user, .. } => {
+            user_section(sender_user, "forwarded_from", "Forwarded from", me)
+        }
+        MessageOrigin::HiddenUser { sender_user_name, .. } => {
+            Section::new("forwarded_from", "👤", "Forwarded from")
+                .field(Field::new("origin", Value::Text("hidden_user".to_string())))
+                .field(Field::new("sender_name", Value::Text(sender_user_name.clone())))
+                .field(Field::new("hidden", Value::Bool(true)).note("the sender hides their account in forwards"))
+        }
+        MessageOrigin::Chat { sender_chat, .. } => {
+            // Sent by an anonymous admin on behalf of the chat
+            sender_chat_section(sender_chat, "forwarded_from", "Forwarded from", Some("chat"))
+        }
+        MessageOrigin::Channel { chat, .. } => {
+            sender_chat_section(chat, "forwarded_from", "Forwarded from", Some("channel"))
+        }
+    }
+}
+
+pub fn chat_section(chat: &Chat) -> Section {
+    let mut section = Section::new("chat", "💬", "Chat").field(Field::new("id", Value::Id(chat.id.0)));
+    if let Some(peer) = PeerId::from_bot_api(chat.id.0) {
+        section.push(Field::new("mtproto_id", Value::Peer(peer)));
+    }
+    section.push(Field::new("type", Value::Text(chat_type(chat).to_string())));
+    if let ChatKind::Public(ChatPublic { kind: PublicChatKind::Supergroup(supergroup), .. }) = &chat.kind {
+        section.push(Field::new("is_forum", Value::Bool(supergroup.is_forum)));
+    }
+    if let Some(title) = chat.title() {
+        section.push(Field::new("title", Value::Text(title.to_string())));
+    }
+    if let Some(username) = chat.username() {
+        section.push(Field::new("username", Value::Code(username.to_string())));
+    }
+    section
+}
+
+/// A user or bot shared through the picker. Telegram only sends the fields the
+/// button requested, so this is rendered with the regular user section where possible.
+pub fn shared_user_section(shared: &SharedUser, is_bot: bool, me: Option<&Me>) -> Section {
+    let user = User {
+        id: shared.user_id,
+        is_bot,
+        first_name: shared.first_name.clone().unwrap_or_default(),
+        last_name: shared.last_name.clone(),
+        username: shared.username.clone(),
+        language_code: None,
+        is_premium: false,
+        added_to_attachment_menu: false,
+    };
+    let mut section = if is_bot {
+        user_section(&user, "shared_bot", "Shared bot", me)
+    } else {
+        user_section(&user, "shared_user", "Shared user", me)
+    };
+    // Sharing never includes these, so the defaults above would be guesses
+    section.fields.retain(|field| !matches!(field.key, "is_premium" | "added_to_attachment_menu"));
+    section
+}
+
+/// A group or channel shared through the picker.
+pub fn shared_chat_section(shared: &ChatShared, is_channel: bool) -> Section {
+    let mut section = if is_channel {
+        Section::new("shared_channel", "💬", "Shared channel")
+    } else {
+        Section::new("shared_group", "💬", "Shared group")
+    };
+    section.push(Field::new("id", Value::Id(shared.chat_id.0)));
+    if let Some(peer) = PeerId::from_bot_api(shared.chat_id.0) {
+        section.push(Field::new("mtproto_id", Value::Peer(peer)));
+    }
+    if let Some(chat_title) = &shared.title {
+        section.push(Field::new("title", Value::Text(chat_title.clone())));
+    }
+    if let Some(username) = &shared.username {
+        section.push(Field::new("username", Value::Code(username.clone())));
+    }
+    section
+}
+
+/// Bot API names of the chat permissions, in the order Telegram documents them.
+const PERMISSIONS: [(&str, ChatPermissions); 14] = [
+    ("can_send_messages", ChatPermissions::SEND_MESSAGES),
+    ("can_send_audios", ChatPermissions::SEND_AUDIOS),
+    ("can_send_documents", ChatPermissions::SEND_DOCUMENTS),
+    ("can_send_photos", ChatPermissions::SEND_PHOTOS),
+    ("can_send_videos", ChatPermissions::SEND_VIDEOS),
+    ("can_send_video_notes", ChatPermissions::SEND_VIDEO_NOTES),
+    ("can_send_voice_notes", ChatPermissions::SEND_VOICE_NOTES),
+    ("can_send_polls", ChatPermissions::SEND_POLLS),
+    ("can_send_other_messages", ChatPermissions::SEND_OTHER_MESSAGES),
+    ("can_add_web_page_previews", ChatPermissions::ADD_WEB_PAGE_PREVIEWS),
+    ("can_change_info", ChatPermissions::CHANGE_INFO),
+    ("can_invite_users", ChatPermissions::INVITE_USERS),
+    ("can_pin_messages", ChatPermissions::PIN_MESSAGES),
+    ("can_manage_topics", ChatPermissions::MANAGE_TOPICS),
+];
+
+/// One `can_…: true/false` field per permission.
+pub fn permission_fields(permissions: ChatPermissions) -> Vec<Field> {
+    PERMISSIONS
+        .iter()
+        .map(|(key, permission)| Field::new(key, Value::Bool(permissions.contains(permission.clone()))))
+        .collect()
+}
+
+fn full_chat_type(info: &ChatFullInfo) -> &'static str {
+    match &info.kind {
+        ChatFullInfoKind::Private(_) => "private",
+        ChatFullInfoKind::Public(public) => match public.kind {
+            ChatFullInfoPublicKind::Group(_) => "group",
+            ChatFullInfoPublicKind::Supergroup(_) => "supergroup",
+            ChatFullInfoPublicKind::Channel(_) => "channel",
+        },
+    }
+}
+
+fn push_seconds(section: &mut Section, key: &'static str, seconds: Option<Seconds>) {
+    if let Some(seconds) = seconds {
+        section.push(Field::new(key, Value::Number(seconds.seconds() as i64)).note("seconds"));
+    }
+}
+
+fn push_flag(section: &mut Section, key: &'static str, value: bool) {
+    // Telegram omits false flags, so only the ones that are set are worth a line
+    if value {
+        section.push(Field::new(key, Value::Bool(true)));
+    }
+}
+
+/// `/chat`: everything getChat returns about a chat. Fields Telegram leaves
+/// out, because they are unset or the bot may not see them, are left out too.
+pub fn chat_full_section(info: &ChatFullInfo) -> Section {
+    let mut section = Section::new("chat_full", "💬", "Chat details").field(Field::new("id", Value::Id(info.id.0)));
+    if let Some(peer) = PeerId::from_bot_api(info.id.0) {
+        section.push(Field::new("mtproto_id", Value::Peer(peer)));
+    }
+    section.push(Field::new("type", Value::Text(full_chat_type(info).to_string())));
+    push_optional_text(&mut section, "title", info.title());
+    push_optional_text(&mut section, "first_name", info.first_name());
+    push_optional_text(&mut section, "last_name", info.last_name());
+    if let Some(username) = info.username() {
+        section.push(Field::new("username", Value::Code(username.to_string())));
+    }
+    let active_usernames = match &info.kind {
+        ChatFullInfoKind::Public(public) => match &public.kind {
+            ChatFullInfoPublicKind::Supergroup(ChatFullInfoPublicSupergroup { active_usernames, .. }) => active_usernames.as_ref(),
+            _ => None,
+        },
+        ChatFullInfoKind::Private(_) => None,
+    };
+    if let Some(usernames) = active_usernames {
+        let usernames = usernames.iter().map(|username| Value::Code(username.clone())).collect();
+        section.push(Field::new("active_usernames", Value::List(usernames)));
+    }
+    push_optional_text(&mut section, "bio", info.bio());
+    push_optional_text(&mut section, "description", info.description());
+    if let Some(invite_link) = info.invite_link() {
+        section.push(Field::new("invite_link", Value::Link(invite_link.to_string())));
+    }
+
+    if let Some(pinned) = &info.pinned_message {
+        let mut fields = vec![
+            Field::new("message_id", Value::Id(pinned.id.0 as i64)),
+            Field::new("date", Value::Date(pinned.date)),
+        ];
+        if let Some(url) = message_link(&pinned.chat, pinned.id.0) {
+            fields.push(Field::new("link", Value::Link(url)));
+        }
+        section.push(Field::new("pinned_message", Value::Fields(fields)));
+    }
+    if let Some(id) = info.linked_chat_id() {
+        let field = Field::new("linked_chat_id", Value::Id(id));
+        match PeerId::from_bot_api(id) {
+            Some(peer) => section.push(field.note(peer.to_string())),
+            None => section.push(field),
+        }
+    }
+    if let Some(permissions) = info.permissions() {
+        section.push(Field::new("permissions", Value::Fields(permission_fields(permissions))));
+    }
+    push_seconds(&mut section, "slow_mode_delay", info.slow_mode_delay());
+    push_seconds(&mut section, "message_auto_delete_time", info.message_auto_delete_time);
+
+    if let Some(emoji_id) = &info.emoji_status_custom_emoji_id {
+        let mut fields = vec![Field::new("custom_emoji_id", Value::Code(emoji_id.to_string()))];
+        if let Some(expires) = info.emoji_status_expiration_date {
+            fields.push(Field::new("expiration_date", Value::Date(expires)));
+        }
+        section.push(Field::new("emoji_status", Value::Fields(fields)));
+    }
+    if let Some(color) = info.accent_color_id {
+        section.push(Field::new("accent_color_id", Value::Number(color.into())));
+    }
+    if let Some(color) = info.profile_accent_color_id {
+        section.push(Field::new("profile_accent_color_id", Value::Number(color.into())));
+    }
+    if let Some(emoji_id) = &info.background_custom_emoji_id {
+        section.push(Field::new("background_custom_emoji_id", Value::Code(emoji_id.to_string())));
+    }
+
+    if let Some(location) = info.location() {
+        section.push(Field::new(
+            "location",
+            Value::Fields(vec![
+                Field::new("address", Value::Text(location.address.clone())),
+                Field::new("latitude", Value::Float(location.location.latitude)),
+                Field::new("longitude", Value::Float(location.location.longitude)),
+            ]),
+        ));
+    }
+    if let Some(name) = info.sticker_set_name() {
+        section.push(Field::new("sticker_set_name", Value::Code(name.to_string())));
+    }
+    if let Some(name) = info.custom_emoji_sticker_set_name() {
+        section.push(Field::new("custom_emoji_sticker_set_name", Value::Code(name.to_string())));
+    }
+
+    push_flag(&mut section, "has_protected_content", info.has_protected_content());
+    push_flag(&mut section, "join_to_send_messages", info.join_to_send_messages());
+    push_flag(&mut section, "join_by_request", info.join_by_request());
+    push_flag(&mut section, "has_hidden_members", info.has_hidden_members);
+    push_flag(&mut section, "has_aggressive_anti_spam_enabled", info.has_aggressive_anti_spam_enabled);
+    push_flag(&mut section, "has_private_forwards", info.has_private_forwards());
+    section
+}
+
+fn member_status(kind: &ChatMemberKind) -> &'static str {
+    // The names Telegram uses in the status field
+    match kind {
+        ChatMemberKind::Owner(_) => "creator",
+        ChatMemberKind::Administrator(_) => "administrator",
+        ChatMemberKind::Member(_) => "member",
+        ChatMemberKind::Restricted(_) => "restricted",
+        ChatMemberKind::Left => "left",
+        ChatMemberKind::Banned(_) => "kicked",
+    }
+}
+
+fn until_date_field(key: &'static str, until_date: UntilDate) -> Field {
+    match until_date {
+        UntilDate::Date(date) => Field::new(key, Value::Date(date)),
+        UntilDate::Forever => Field::new(key, Value::Text("forever".to_string())),
+    }
+}
+
+fn admin_rights(admin: &Administrator) -> Vec<Field> {
+    [
+        ("can_manage_chat", admin.can_manage_chat),
+        ("can_delete_messages", admin.can_delete_messages),
+        ("can_manage_video_chats", admin.can_manage_video_chats),
+        ("can_restrict_members", admin.can_restrict_members),
+        ("can_promote_members", admin.can_promote_members),
+        ("can_change_info", admin.can_change_info),
+        ("can_invite_users", admin.can_invite_users),
+        ("can_post_stories", admin.can_post_stories),
+        ("can_edit_stories", admin.can_edit_stories),
+        ("can_delete_stories", admin.can_delete_stories),
+        ("can_post_messages", admin.can_post_messages),
+        ("can_edit_messages", admin.can_edit_messages),
+        ("can_pin_messages", admin.can_pin_messages),
+        ("can_manage_topics", admin.can_manage_topics),
+    ]
+    .into_iter()
+    .map(|(key, value)| Field::new(key, Value::Bool(value)))
+    .collect()
+}
+
+fn restricted_permissions(restricted: &Restricted) -> ChatPermissions {
+    let mut permissions = ChatPermissions::empty();
+    for (flag, value) in [
+        (ChatPermissions::SEND_MESSAGES, restricted.can_send_messages),
+        (ChatPermissions::SEND_AUDIOS, restricted.can_send_audios),
+        (ChatPermissions::SEND_DOCUMENTS, restricted.can_send_documents),
+        (ChatPermissions::SEND_PHOTOS, restricted.can_send_photos),
+        (ChatPermissions::SEND_VIDEOS, restricted.can_send_videos),
+        (ChatPermissions::SEND_VIDEO_NOTES, restricted.can_send_video_notes),
+        (ChatPermissions::SEND_VOICE_NOTES, restricted.can_send_voice_notes),
+        (ChatPermissions::SEND_POLLS, restricted.can_send_polls),
+        (ChatPermissions::SEND_OTHER_MESSAGES, restricted.can_send_other_messages),
+        (ChatPermissions::ADD_WEB_PAGE_PREVIEWS, restricted.can_add_web_page_previews),
+        (ChatPermissions::CHANGE_INFO, restricted.can_change_info),
+        (ChatPermissions::INVITE_USERS, restricted.can_invite_users),
+        (ChatPermissions::PIN_MESSAGES, restricted.can_pin_messages),
+        (ChatPermissions::MANAGE_TOPICS, restricted.can_manage_topics),
+    ] {
+        permissions.set(flag, value);
+    }
+    permissions
+}
+
+/// `/whois`: a user's status in a group as getChatMember reports it, with the
+/// rights of an admin or the restrictions of a restricted member.
+pub fn member_section(member: &ChatMember) -> Section {
+    let mut section = Section::new("member", "🛡", "Member")
+        .field(Field::new("user_id", Value::Id(member.user.id.0 as i64)))
+        .field(Field::new("status", Value::Text(member_status(&member.kind).to_string())));
+    if let Some(title) = member.custom_title() {
+        section.push(Field::new("custom_title", Value::Text(title.to_string())));
+    }
+
+    match &member.kind {
+        ChatMemberKind::Owner(owner) => {
+            section.push(Field::new("is_anonymous", Value::Bool(owner.is_anonymous)));
+            section.push(Field::new("admin_rights", Value::Text("all".to_string())).note("the owner holds every right"));
+        }
+        ChatMemberKind::Administrator(admin) => {
+            section.push(Field::new("is_anonymous", Value::Bool(admin.is_anonymous)));
+            section.push(Field::new("can_be_edited", Value::Bool(admin.can_be_edited)).note("whether the bot may change these rights"));
+            section.push(Field::new("admin_rights", Value::Fields(admin_rights(admin))));
+        }
+        ChatMemberKind::Member(member) => {
+            if let Some(until_date) = member.until_date {
+                section.push(until_date_field("until_date", until_date).note("the subscription ends"));
+            }
+        }
+        ChatMemberKind::Restricted(restricted) => {
+            section.push(Field::new("is_member", Value::Bool(restricted.is_member)));
+            section.push(Field::new("restrictions", Value::Fields(permission_fields(restricted_permissions(restricted)))));
+            section.push(until_date_field("until_date", restricted.until_date));
+        }
+        ChatMemberKind::Left => {}
+        ChatMemberKind::Banned(banned) => {
+            section.push(until_date_field("until_date", banned.until_date));
+        }
+    }
+    section
+}
+
+/// Analysis of a bare numeric ID, as sent with `/id` or on its own.
+pub fn id_section(id: i64) -> Section {
+    let mut section = Section::new("id", "🔢", "ID").field(Field::new("id", Value::Id(id)));
+
+    let Some(peer) = PeerId::from_bot_api(id) else {
+        return section.field(Field::new("peer", Value::None).note("outside every Bot API ID range"));
+    };
+
+    let kind = peer.kind();
+    section.push(Field::new("mtproto_id", Value::Peer(peer)));
+    section.push(Field::new("peer", Value::Text(kind.to_string())).note(kind.explanation()));
+    section.push(Field::new("range", Value::Text(peer.range())));
+    if kind == PeerKind::User {
+        section.push(Field::new("created", Value::Age(get_age_estimate(peer.raw()))));
+    }
+    section
+}
+
+/// Public link to a message, `t.me/<username>/<id>` for public chats and
+/// `t.me/c/<mtproto id>/<id>` (members only) for private supergroups and channels.
+pub fn message_link(chat: &Chat, message_id: i32) -> Option<String> {
+    if let Some(username) = chat.username() {
+        return Some(format!("https://t.me/{}/{}", username, message_id));
+    }
+    match PeerId::from_bot_api(chat.id.0)? {
+        PeerId::Channel(raw) => Some(format!("https://t.me/c/{}/{}", raw, message_id)),
+        _ => None,
+    }
+}
+
+/// The "📃 Message" section describing a forward: when the original was sent,
+/// the channel post it came from, its author signature and a link to it.
+pub fn forward_message_section(origin: &MessageOrigin, is_automatic_forward: bool) -> Section {
+    let mut section = Section::new("message", "📃", "Message").field(Field::new("forward_date", Value::Date(origin.date())));
+
+    let author_signature = match origin {
+        MessageOrigin::Channel { chat, message_id, author_signature, .. } => {
+            section.push(Field::new("message_id", Value::Id(message_id.0 as i64)));
+            if let Some(url) = message_link(chat, message_id.0) {
+                section.push(Field::new("link", Value::Link(url)));
+            }
+            author_signature
+        }
+        MessageOrigin::Chat { author_signature, .. } => author_signature,
+        MessageOrigin::User { .. } | MessageOrigin::HiddenUser { .. } => &None,
+    };
+    if let Some(signature) = author_signature {
+        section.push(Field::new("author_signature", Value::Text(signature.clone())));
+    }
+    if is_automatic_forward {
+        section.push(Field::new("automatic_forward", Value::Bool(true)).note("channel post copied to its discussion group"));
+    }
+    section
+}
+
+/// `1.5 MiB`
+fn human_size(bytes: u32) -> String {
+    const UNITS: [&str; 3] = ["KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    if size < 1024.0 {
+        return format!("{} B", bytes);
+    }
+    let mut unit = "B";
+    for next in UNITS {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next;
+    }
+    format!("{:.1} {}", size, unit)
+}
+
+fn file_fields(file: &FileMeta) -> Vec<Field> {
+    vec![
+        Field::new("file_id", Value::Code(file.id.to_string())),
+        Field::new("file_unique_id", Value::Code(file.unique_id.to_string())),
+        Field::new("file_size", Value::Number(file.size as i64)).note(human_size(file.size)),
+    ]
+}
+
+fn push_file(section: &mut Section, file: &FileMeta) {
+    section.fields.extend(file_fields(file));
+}
+
+fn push_dimensions(section: &mut Section, width: u32, height: u32) {
+    section.push(Field::new("width", Value::Number(width as i64)));
+    section.push(Field::new("height", Value::Number(height as i64)));
+}
+
+/// `90×90, 320×320, 800×800`
+fn photo_sizes(sizes: &[PhotoSize]) -> String {
+    sizes.iter().map(|size| format!("{}×{}", size.width, size.height)).collect::<Vec<_>>().join(", ")
+}
+
+fn push_duration(section: &mut Section, duration: Seconds) {
+    let seconds = duration.seconds();
+    section.push(Field::new("duration", Value::Number(seconds as i64)).note(format!("{}:{:02}", seconds / 60, seconds % 60)));
+}
+
+fn push_optional_text(section: &mut Section, key: &'static str, value: Option<impl ToString>) {
+    if let Some(value) = value {
+        section.push(Field::new(key, Value::Text(value.to_string())));
+    }
+}
+
+fn sticker_type(kind: &StickerKind) -> &'static str {
+    match kind {
+        StickerKind::Regular { .. } => "regular",
+        StickerKind::Mask { .. } => "mask",
+        StickerKind::CustomEmoji { .. } => "custom_emoji",
+    }
+}
+
+fn sticker_format(format: StickerFormat) -> &'static str {
+    match format {
+        StickerFormat::Static => "static",
+        StickerFormat::Animated => "animated",
+        StickerFormat::Video => "video",
+    }
+}
+
+fn mask_point(point: &MaskPoint) -> &'static str {
+    match point {
+        MaskPoint::Forehead => "forehead",
+        MaskPoint::Eyes => "eyes",
+        MaskPoint::Mouth => "mouth",
+        MaskPoint::Chin => "chin",
+    }
+}
+
+/// Sticker details beyond the file: its emoji, type and format, and whatever
+/// only that type of sticker has.
+fn sticker_fields(sticker: &Sticker) -> Vec<Field> {
+    let mut fields = Vec::new();
+    if let Some(emoji) = &sticker.emoji {
+        fields.push(Field::new("emoji", Value::Text(emoji.clone())));
+    }
+    fields.push(Field::new("sticker_type", Value::Text(sticker_type(&sticker.kind).to_string())));
+    fields.push(Field::new("format", Value::Text(sticker_format(sticker.format()).to_string())));
+    match &sticker.kind {
+        StickerKind::Regular { premium_animation: Some(animation) } => {
+            fields.push(Field::new("premium_animation", Value::Fields(file_fields(animation))));
+        }
+        StickerKind::Regular { premium_animation: None } => {}
+        StickerKind::Mask { mask_position } => fields.push(Field::new(
+            "mask_position",
+            Value::Fields(vec![
+                Field::new("point", Value::Text(mask_point(&mask_position.point).to_string())),
+                Field::new("x_shift", Value::Float(mask_position.x_shift)),
+                Field::new("y_shift", Value::Float(mask_position.y_shift)),
+                Field::new("scale", Value::Float(mask_position.scale)),
+            ]),
+        )),
+        StickerKind::CustomEmoji { custom_emoji_id } => {
+            fields.push(Field::new("custom_emoji_id", Value::Code(custom_emoji_id.to_string())));
+        }
+    }
+    if sticker.needs_repainting {
+        fields.push(Field::new("needs_repainting", Value::Bool(true)).note("recoloured to match the text"));
+    }
+    fields
+}
+
+/// The "📎 Media" section of a message with an attachment, with the IDs other
+/// bots need to send the same file again.
+pub fn media_section(msg: &Message) -> Option<Section> {
+    let section = |kind: &str| {
+        Section::new("media", "📎", "Media").field(Field::new("type", Value::Text(kind.to_string())))
+    };
+
+    if let Some(sizes) = msg.photo() {
+        // Telegram lists the sizes from smallest to largest
+        let largest = sizes.last()?;
+        let mut media = section("photo");
+        push_file(&mut media, &largest.file);
+        push_dimensions(&mut media, largest.width, largest.height);
+        media.push(Field::new("sizes", Value::Text(photo_sizes(sizes))).note("the file_id above is the largest"));
+        return Some(media);
+    }
+    if let Some(video) = msg.video() {
+        let mut media = section("video");
+        push_file(&mut media, &video.file);
+        push_dimensions(&mut media, video.width, video.height);
+        push_duration(&mut media, video.duration);
+        push_optional_text(&mut media, "mime_type", video.mime_type.as_ref());
+        push_optional_text(&mut media, "file_name", video.file_name.as_ref());
+        return Some(media);
+    }
+    if let Some(animation) = msg.animation() {
+        let mut media = section("animation");
+        push_file(&mut media, &animation.file);
+        push_dimensions(&mut media, animation.width, animation.height);
+        push_duration(&mut media, animation.duration);
+        push_optional_text(&mut media, "mime_type", animation.mime_type.as_ref());
+        push_optional_text(&mut media, "file_name", animation.file_name.as_ref());
+        return Some(media);
+    }
+    if let Some(video_note) = msg.video_note() {
+        let mut media = section("video_note");
+        push_file(&mut media, &video_note.file);
+        push_dimensions(&mut media, video_note.length, video_note.length);
+        push_duration(&mut media, video_note.duration);
+        return Some(media);
+    }
+    if let Some(audio) = msg.audio() {
+        let mut media = section("audio");
+        push_file(&mut media, &audio.file);
+        push_duration(&mut media, audio.duration);
+        push_optional_text(&mut media, "mime_type", audio.mime_type.as_ref());
+        push_optional_text(&mut media, "file_name", audio.file_name.as_ref());
+        push_optional_text(&mut media, "performer", audio.performer.as_ref());
+        push_optional_text(&mut media, "title", audio.title.as_ref());
+        return Some(media);
+    }
+    if let Some(voice) = msg.voice() {
+        let mut media = section("voice");
+        push_file(&mut media, &voice.file);
+        push_duration(&mut media, voice.duration);
+        push_optional_text(&mut media, "mime_type", voice.mime_type.as_ref());
+        return Some(media);
+    }
+    if let Some(document) = msg.document() {
+        let mut media = section("document");
+        push_file(&mut media, &document.file);
+        push_optional_text(&mut media, "mime_type", document.mime_type.as_ref());
+        push_optional_text(&mut media, "file_name", document.file_name.as_ref());
+        return Some(media);
+    }
+    if let Some(sticker) = msg.sticker() {
+        let mut media = section("sticker");
+        push_file(&mut media, &sticker.file);
+        push_dimensions(&mut media, sticker.width.into(), sticker.height.into());
+        match &sticker.set_name {
+            Some(set_name) => media.push(Field::new("set_name", Value::Code(set_name.clone()))),
+            None => media.push(Field::new("set_name", Value::None).note("not part of a set")),
+        }
+        media.fields.extend(sticker_fields(sticker));
+        return Some(media);
+    }
+    None
+}
+
+/// The text or caption of a message with its entities, and which of the two it is.
+fn message_entities(msg: &Message) -> Option<(&'static str, &str, &[MessageEntity])> {
+    match (msg.text(), msg.entities(), msg.caption(), msg.caption_entities()) {
+        (Some(text), Some(entities), _, _) => Some(("text", text, entities)),
+        (_, _, Some(caption), Some(entities)) => Some(("caption", caption, entities)),
+        _ => None,
+    }
+}
+
+/// The "✨ Custom emoji" section listing every custom emoji in the text or
+/// caption, with UTF-16 offsets as the Bot API counts them.
+pub fn custom_emoji_section(msg: &Message) -> Option<Section> {
+    let (_, text, entities) = message_entities(msg)?;
+
+    let emoji = entities
+        .iter()
+        .zip(MessageEntityRef::parse(text, entities))
+        .filter_map(|(entity, entity_ref)| match &entity.kind {
+            MessageEntityKind::CustomEmoji { custom_emoji_id } => Some(Value::Fields(vec![
+                Field::new("emoji", Value::Text(entity_ref.text().to_string())),
+                Field::new("offset", Value::Number(entity.offset as i64)),
+                Field::new("length", Value::Number(entity.length as i64)),
+                Field::new("custom_emoji_id", Value::Code(custom_emoji_id.to_string())),
+            ])),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if emoji.is_empty() {
+        return None;
+    }
+
+    Some(
+        Section::new("custom_emoji", "✨", "Custom emoji")
+            .field(Field::new("count", Value::Number(emoji.len() as i64)))
+            .field(Field::new("entities", Value::List(capped(emoji)))),
+    )
+}
+
+fn entity_type(kind: &MessageEntityKind) -> &'static str {
+    match kind {
+        MessageEntityKind::Mention => "mention",
+        MessageEntityKind::Hashtag => "hashtag",
+        MessageEntityKind::Cashtag => "cashtag",
+        MessageEntityKind::BotCommand => "bot_command",
+        MessageEntityKind::Url => "url",
+        MessageEntityKind::Email => "email",
+        MessageEntityKind::PhoneNumber => "phone_number",
+        MessageEntityKind::Bold => "bold",
+        MessageEntityKind::Blockquote => "blockquote",
+        MessageEntityKind::ExpandableBlockquote => "expandable_blockquote",
+        MessageEntityKind::Italic => "italic",
+        MessageEntityKind::Underline => "underline",
+        MessageEntityKind::Strikethrough => "strikethrough",
+        MessageEntityKind::Spoiler => "spoiler",
+        MessageEntityKind::Code => "code",
+        MessageEntityKind::Pre { .. } => "pre",
+        MessageEntityKind::TextLink { .. } => "text_link",
+        MessageEntityKind::TextMention { .. } => "text_mention",
+        MessageEntityKind::CustomEmoji { .. } => "custom_emoji",
+    }
+}
+
+/// Longest entity text shown, in characters, so a long quote cannot push the
+/// answer over Telegram's message limit.
+const MAX_ENTITY_TEXT: usize = 64;
+
+/// Most entities listed in one answer; a heavily formatted message can carry
+/// hundreds. Long entities can still overflow the message limit, so
+/// [`OutputFormat::render`](crate::render::OutputFormat::render) shortens the
+/// list further where needed.
+pub const MAX_LISTED_ENTITIES: usize = 16;
+
+/// The first [`MAX_LISTED_ENTITIES`] items, followed by `… and N more` if
+/// some were left out.
+fn capped(mut items: Vec<Value>) -> Vec<Value> {
+    if items.len() > MAX_LISTED_ENTITIES {
+        let more = items.len() - MAX_LISTED_ENTITIES;
+        items.truncate(MAX_LISTED_ENTITIES);
+        items.push(Value::More(more));
+    }
+    items
+}
+
+/// The "🔤 Entities" section: every formatting entity of the text or caption
+/// with its UTF-16 offset and length, the text it covers and what it points to.
+pub fn entities_section(msg: &Message) -> Option<Section> {
+    let (source, text, entities) = message_entities(msg)?;
+    if entities.is_empty() {
+        return None;
+    }
+
+    let items = entities
+        .iter()
+        .zip(MessageEntityRef::parse(text, entities))
+        .map(|(entity, entity_ref)| {
+            let covered = entity_ref.text();
+            let covered = match covered.char_indices().nth(MAX_ENTITY_TEXT) {
+                Some((end, _)) => format!("{}…", &covered[..end]),
+                None => covered.to_string(),
+            };
+
+            let mut fields = vec![
+                Field::new("type", Value::Text(entity_type(&entity.kind).to_string())),
+                Field::new("offset", Value::Number(entity.offset as i64)),
+                Field::new("length", Value::Number(entity.length as i64)),
+                Field::new("text", Value::Text(covered)),
+            ];
+            match &entity.kind {
+                MessageEntityKind::TextLink { url } => fields.push(Field::new("url", Value::Link(url.to_string()))),
+                MessageEntityKind::TextMention { user } => {
+                    fields.push(Field::new("user_id", Value::Id(user.id.0 as i64)));
+                    fields.push(Field::new("user", Value::Mention { user_id: user.id, name: user.full_name() }));
+                }
+                MessageEntityKind::Pre { language: Some(language) } => {
+                    fields.push(Field::new("language", Value::Text(language.clone())));
+                }
+                MessageEntityKind::CustomEmoji { custom_emoji_id } => {
+                    fields.push(Field::new("custom_emoji_id", Value::Code(custom_emoji_id.to_string())));
+                }
+                _ => {}
+            }
+            Value::Fields(fields)
+        })
+        .collect::<Vec<_>>();
+
+    Some(
+        Section::new("entities", "🔤", "Entities")
+            .field(Field::new("source", Value::Text(source.to_string())))
+            .field(Field::new("count", Value::Number(items.len() as i64)))
+            .field(Field::new("entities", Value::List(capped(items)))),
+    )
+}
+
+/// One page of a sticker set: stickers `start..end`, with the set's name,
+/// title and link on the first page.
+fn sticker_set_page(set: &StickerSet, start: usize, end: usize) -> Report {
+    let title = format!("Sticker set ({}–{} of {})", start + 1, end, set.stickers.len());
+    let mut section = Section::new("sticker_set", "🗂", title);
+    if start == 0 {
+        section.push(Field::new("name", Value::Code(set.name.clone())));
+        section.push(Field::new("title", Value::Text(set.title.clone())));
+        section.push(Field::new("link", Value::Link(format!("https://t.me/addstickers/{}", set.name))));
+    }
+
+    let items = set.stickers[start..end]
+        .iter()
+        .map(|sticker| {
+            let mut fields = vec![Field::new("emoji", Value::Text(sticker.emoji.clone().unwrap_or_default()))];
+            fields.push(Field::new("file_id", Value::Code(sticker.file.id.to_string())));
+            fields.push(Field::new("file_unique_id", Value::Code(sticker.file.unique_id.to_string())));
+            if let Some(custom_emoji_id) = sticker.kind.custom_emoji_id() {
+                fields.push(Field::new("custom_emoji_id", Value::Code(custom_emoji_id.to_string())));
+            }
+            Value::Fields(fields)
+        })
+        .collect();
+    section.push(Field::new("stickers", Value::List(items)));
+    section.into()
+}
+
+/// `/stickerset`: the set itself, then every sticker's file_id, one report per
+/// message. Each page takes as many stickers as fit into one message when
+/// rendered in `format`, so none are cut off.
+pub fn sticker_set_reports(set: &StickerSet, format: OutputFormat) -> Vec<Report> {
+    let fits = |report: &Report| message_length(&format.renderer().render(report)) <= MAX_MESSAGE_LENGTH;
+    let mut reports = Vec::new();
+
+    let mut start = 0;
+    while start < set.stickers.len() {
+        let mut end = start + 1;
+        while end < set.stickers.len() && fits(&sticker_set_page(set, start, end + 1)) {
+            end += 1;
+        }
+        reports.push(sticker_set_page(set, start, end));
+        start = end;
+    }
+
+    if reports.is_empty() {
+        reports.push(
+            Section::new("sticker_set", "🗂", "Sticker set")
+                .field(Field::new("name", Value::Code(set.name.clone())))
+                .field(Field::new("title", Value::Text(set.title.clone())))
+                .field(Field::new("stickers", Value::List(Vec::new())))
+                .into(),
+        );
+    }
+    reports
+}
+
+/// Profile photos `/photos` lists and sends, the most a media group holds.
+pub const MAX_PROFILE_PHOTOS: u8 = 10;
+
+/// Accounts estimated to be younger than this count as recent.
+const RECENT_ACCOUNT_DAYS: i64 = 180;
+
+/// Why a photo count stands out: spam accounts tend to be new and have no photo.
+fn photo_count_note(user_id: UserId, total_count: u32) -> Option<String> {
+    let created = get_age_estimate(user_id.0).date;
+    (total_count == 0 && Utc::now() - created < Duration::days(RECENT_ACCOUNT_DAYS))
+        .then(|| "no photos and a recent ID, a common sign of spam accounts".to_string())
+}
+
+/// Adds the number of profile photos of `user_id` to every section about that
+/// user, right before the age estimate it is read together with.
+pub fn push_profile_photos(report: &mut Report, user_id: UserId, total_count: u32) {
+    for section in &mut report.sections {
+        if !section.fields.iter().any(|field| field.key == "id" && field.value == Value::Id(user_id.0 as i64)) {
+            continue;
+        }
+        if let Some(created) = section.fields.iter().position(|field| field.key == "created") {
+            let mut field = Field::new("profile_photos", Value::Number(total_count.into()));
+            field.note = photo_count_note(user_id, total_count);
+            section.fields.insert(created, field);
+        }
+    }
+}
+
+/// IDs of the users a report describes, whose profile photos can be counted.
+pub fn reported_users(report: &Report) -> Vec<UserId> {
+    let mut users = Vec::new();
+    for section in &report.sections {
+        if !section.fields.iter().any(|field| field.key == "created") {
+            continue;
+        }
+        for field in &section.fields {
+            if let (Value::Id(id @ 1..), "id") = (&field.value, field.key) {
+                if !users.contains(&UserId(*id as u64)) {
+                    users.push(UserId(*id as u64));
+                }
+            }
+        }
+    }
+    users
+}
+
+/// `/photos`: how many profile photos a user has and, for the latest ones, the
+/// IDs of the largest size with every size Telegram keeps.
+pub fn profile_photos_section(user_id: UserId, photos: &UserProfilePhotos) -> Section {
+    let mut total_count = Field::new("total_count", Value::Number(photos.total_count.into()));
+    if let Some(note) = photo_count_note(user_id, photos.total_count) {
+        total_count = total_count.note(note);
+    } else if photos.photos.len() < photos.total_count as usize {
+        total_count = total_count.note(format!("showing the latest {}", photos.photos.len()));
+    }
+
+    let items = photos
+        .photos
+        .iter()
+        .enumerate()
+        .filter_map(|(index, sizes)| {
+            // Sizes come from smallest to largest
+            let largest = sizes.last()?;
+            let mut fields = vec![Field::new("photo", Value::Number(index as i64 + 1))];
+            fields.extend(file_fields(&largest.file));
+            fields.push(Field::new("sizes", Value::Text(photo_sizes(sizes))));
+            Some(Value::Fields(fields))
+        })
+        .collect();
+
+    Section::new("profile_photos", "🖼", "Profile photos")
+        .field(Field::new("user_id", Value::Id(user_id.0 as i64)))
+        .field(total_count)
+        .field(Field::new("photos", Value::List(items)))
+}
+
+/// Builds the full analysis shown in reply to a regular message: the sender,
+/// the chat it was sent in, for forwards where it originally came from, its
+/// attachment and its custom emoji or, in detail, all its formatting entities.
+pub fn message_report(msg: &Message, verbosity: Verbosity, me: Option<&Me>) -> Report {
+    let mut report = Report::new();
+
+    if let Some(user) = &msg.from {
+        report.push(user_section(user, "you", "You", me));
+    }
+
+    match verbosity {
+        Verbosity::Brief => {
+            if let Some(origin) = msg.forward_origin() {
+                report.push(forwarded_from_section(origin, me));
+            }
+        }
+        Verbosity::Detailed => {
+            report.push(chat_section(&msg.chat));
+            report.extend(forward_report(msg, me));
+        }
+    }
+    if let Some(media) = media_section(msg) {
+        report.push(media);
+    }
+    match verbosity {
+        Verbosity::Brief => {
+            if let Some(emoji) = custom_emoji_section(msg) {
+                report.push(emoji);
+            }
+        }
+        Verbosity::Detailed => report.extend(entities_report(msg)),
+    }
+
+    report
+}
+
+/// The entities of a message, followed by its custom emoji when the entity
+/// list is too long to show them all.
+fn entities_report(msg: &Message) -> Report {
+    let Some(entities) = entities_section(msg) else {
+        return Report::new();
+    };
+    let mut report = Report::from(entities);
+    // Otherwise the entities already list every custom emoji
+    let truncated = message_entities(msg).is_some_and(|(_, _, entities)| entities.len() > MAX_LISTED_ENTITIES);
+    if let Some(emoji) = custom_emoji_section(msg).filter(|_| truncated) {
+        report.push(emoji);
+    }
+    report
+}
+
+/// The "Forwarded from" and "📃 Message" sections of a forwarded message, or
+/// nothing if the message is not a forward.
+fn forward_report(msg: &Message, me: Option<&Me>) -> Report {
+    let Some(origin) = msg.forward_origin() else {
+        return Report::new();
+    };
+
+    Report::from_iter([
+        forwarded_from_section(origin, me),
+        forward_message_section(origin, msg.is_automatic_forward()),
+    ])
+}
+
+/// Analysis of a message someone replied to with `/id` or a mention: who sent
+/// it, for forwards where it originally came from, its attachment and its
+/// formatting entities.
+pub fn replied_message_report(msg: &Message, me: Option<&Me>) -> Report {
+    let mut report = Report::from(match (&msg.sender_chat, &msg.from) {
+        (Some(chat), _) => sender_chat_section(chat, "sender_chat", "Sender chat", None),
+        (None, Some(user)) => user_section(user, "sender", "Sender", me),
+        (None, None) => Section::new("sender", "👤", "Sender").field(Field::new("id", Value::None).note("unknown")),
+    });
+    report.extend(forward_report(msg, me));
+    if let Some(media) = media_section(msg) {
+        report.push(media);
+    }
+    report.extend(entities_report(msg));
+    report
+}
+
+/// Where in the chat a message lives: its forum topic and the chat linked to
+/// this one (a channel's discussion group, or a group's channel).
+pub fn chat_context_section(thread_id: Option<ThreadId>, linked_chat_id: Option<i64>) -> Section {
+    let mut section = Section::new("context", "📌", "Context");
+    match thread_id {
+        Some(thread_id) => section.push(Field::new("message_thread_id", Value::Id(thread_id.0 .0 as i64)).note("forum topic")),
+        None => section.push(Field::new("message_thread_id", Value::None).note("not in a topic")),
+    }
+    match linked_chat_id {
+        Some(id) => {
+            let field = Field::new("linked_chat_id", Value::Id(id));
+            match PeerId::from_bot_api(id) {
+                Some(peer) => section.push(field.note(peer.to_string())),
+                None => section.push(field),
+            }
+        }
+        None => section.push(Field::new("linked_chat_id", Value::None)),
+    }
+    section
+}
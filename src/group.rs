@@ -0,0 +1,147 @@
+//! Behaviour of the bot in groups and supergroups.
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use teloxide::types::{ChatId, Me, Message, MessageEntityKind};
+
+use crate::settings::{self, SettingsKey, StoreError};
+
+/// Which messages the bot answers in a group. Private chats always get an answer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupMode {
+    /// Only commands such as `/id` and `/chatid`.
+    Commands,
+    /// Commands, messages mentioning the bot and replies to the bot.
+    #[default]
+    Mentions,
+    /// Every message, like in a private chat.
+    All,
+}
+
+impl GroupMode {
+    pub const ALL: [GroupMode; 3] = [GroupMode::Commands, GroupMode::Mentions, GroupMode::All];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            GroupMode::Commands => "answer commands only",
+            GroupMode::Mentions => "answer commands, mentions and replies to me",
+            GroupMode::All => "analyse every message",
+        }
+    }
+}
+
+impl fmt::Display for GroupMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            GroupMode::Commands => "commands",
+            GroupMode::Mentions => "mentions",
+            GroupMode::All => "all",
+        })
+    }
+}
+
+impl FromStr for GroupMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "commands" => Ok(GroupMode::Commands),
+            "mentions" => Ok(GroupMode::Mentions),
+            "all" => Ok(GroupMode::All),
+            other => Err(format!("unknown group mode: {}", other)),
+        }
+    }
+}
+
+pub async fn group_mode(chat_id: ChatId) -> GroupMode {
+    settings::load(SettingsKey::Chat(chat_id)).await.group_mode
+}
+
+pub async fn set_group_mode(chat_id: ChatId, mode: GroupMode) -> Result<(), StoreError> {
+    settings::update(SettingsKey::Chat(chat_id), move |settings| settings.group_mode = mode).await.map(|_| ())
+}
+
+/// Whether a group message is meant for the bot: it mentions the bot by
+/// username or replies to one of the bot's messages.
+pub fn is_addressed_to(msg: &Message, me: &Me) -> bool {
+    // Only whole mentions count, so `@idbot` is not addressed by `@idbot_clone`
+    let mentioned = msg
+        .parse_entities()
+        .or_else(|| msg.parse_caption_entities())
+        .unwrap_or_default()
+        .iter()
+        .any(|entity| match entity.kind() {
+            MessageEntityKind::Mention => entity.text().trim_start_matches('@').eq_ignore_ascii_case(me.username()),
+            MessageEntityKind::TextMention { user } => user.id == me.id,
+            _ => false,
+        });
+    let replied_to_bot = msg
+        .reply_to_message()
+        .and_then(|reply| reply.from.as_ref())
+        .is_some_and(|user| user.id == me.id);
+
+    mentioned || replied_to_bot
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn me() -> Me {
+        serde_json::from_value(json!({
+            "id": 987654321,
+            "is_bot": true,
+            "first_name": "ID Bot",
+            "username": "IdBot",
+            "can_join_groups": true,
+            "can_read_all_group_messages": false,
+            "supports_inline_queries": true,
+            "can_connect_to_business": false,
+            "has_main_web_app": false,
+        }))
+        .unwrap()
+    }
+
+    /// A group message with `text` and one mention entity per `@` word.
+    fn message(text: &str) -> Message {
+        let mut entities = Vec::new();
+        let mut offset = 0;
+        for word in text.split(' ') {
+            if word.starts_with('@') {
+                entities.push(json!({"type": "mention", "offset": offset, "length": word.encode_utf16().count()}));
+            }
+            offset += word.encode_utf16().count() + 1;
+        }
+        serde_json::from_value(json!({
+            "message_id": 1,
+            "date": 1_600_000_000,
+            "chat": {"id": -1001234567890i64, "type": "supergroup", "title": "Rust"},
+            "from": {"id": 123456789, "is_bot": false, "first_name": "Ada"},
+            "text": text,
+            "entities": entities,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn mention_of_the_bot() {
+        assert!(is_addressed_to(&message("hey @IdBot"), &me()));
+        assert!(is_addressed_to(&message("@idbot what is this"), &me()));
+    }
+
+    #[test]
+    fn mention_of_a_longer_username() {
+        assert!(!is_addressed_to(&message("hey @IdBot_clone"), &me()));
+        assert!(!is_addressed_to(&message("hey @xIdBot"), &me()));
+    }
+
+    #[test]
+    fn username_without_the_at_sign() {
+        // Without the @ Telegram does not mark it as a mention
+        assert!(!is_addressed_to(&message("hey IdBot"), &me()));
+    }
+}
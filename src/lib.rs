@@ -0,0 +1,16 @@
+//! Shared analysis and rendering core for the Telegram ID bot.
+//!
+//! Both the standalone teloxide server (`src/main.rs`) and the Vercel function
+//! (`api/webhook.rs`) dispatch updates through [`bot::handle_update`], so a fix
+//! here reaches both deployments.
+
+pub mod age;
+pub mod answers;
+pub mod bot;
+pub mod format;
+pub mod group;
+pub mod peer;
+pub mod render;
+pub mod report;
+pub mod settings;
+pub mod tree;
@@ -0,0 +1,178 @@
+//! Conversion between Bot API chat IDs and MTProto peer IDs.
+//!
+//! The Bot API squeezes users, basic groups and channels into one signed ID:
+//! users keep their MTProto ID, basic groups are negated and supergroups and
+//! channels are offset by `-10^12`, which is where the `-100` prefix comes from.
+
+use std::fmt;
+
+use crate::age::extrapolate::LEGACY_ID_MAX;
+
+/// Largest MTProto user ID; user IDs have at most 40 significant bits.
+pub const MAX_USER_ID: u64 = (1 << 40) - 1;
+
+/// Largest MTProto basic group ID.
+pub const MAX_CHAT_ID: u64 = 999_999_999_999;
+
+/// Bot API IDs of supergroups and channels are `ZERO_CHANNEL_ID - channel_id`.
+pub const ZERO_CHANNEL_ID: i64 = -1_000_000_000_000;
+
+/// Largest MTProto channel ID that still fits below the secret chat range.
+pub const MAX_CHANNEL_ID: u64 = 1_000_000_000_000 - (1 << 31);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerKind {
+    /// A user or a bot; the two share one ID space.
+    User,
+    /// A basic group.
+    Group,
+    /// A supergroup or a channel; the two share one ID space.
+    Channel,
+}
+
+impl PeerKind {
+    /// Tells which kind of peer a Bot API ID encodes, or `None` when it is outside every range.
+    pub fn of(id: i64) -> Option<Self> {
+        PeerId::from_bot_api(id).map(|peer| peer.kind())
+    }
+
+    /// One-line explanation of how the ID encodes this kind of peer.
+    pub fn explanation(&self) -> &'static str {
+        match self {
+            PeerKind::User => "positive IDs belong to users and bots",
+            PeerKind::Group => "negative IDs without the -100 prefix belong to basic groups",
+            PeerKind::Channel => "IDs starting with -100 belong to supergroups and channels",
+        }
+    }
+}
+
+impl fmt::Display for PeerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PeerKind::User => "user or bot",
+            PeerKind::Group => "group",
+            PeerKind::Channel => "supergroup or channel",
+        })
+    }
+}
+
+/// An MTProto peer: the kind of peer plus its raw, always positive, ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PeerId {
+    User(u64),
+    Chat(u64),
+    Channel(u64),
+}
+
+impl PeerId {
+    /// Decodes a Bot API chat ID. Returns `None` for zero and for IDs outside
+    /// the user, basic group and channel ranges (e.g. secret chats).
+    pub fn from_bot_api(id: i64) -> Option<Self> {
+        if id > 0 {
+            let raw = id as u64;
+            (raw <= MAX_USER_ID).then_some(PeerId::User(raw))
+        } else if id < 0 && id > ZERO_CHANNEL_ID {
+            let raw = id.unsigned_abs();
+            (raw <= MAX_CHAT_ID).then_some(PeerId::Chat(raw))
+        } else if id < ZERO_CHANNEL_ID {
+            let raw = (ZERO_CHANNEL_ID - id) as u64;
+            (raw <= MAX_CHANNEL_ID).then_some(PeerId::Channel(raw))
+        } else {
+            None
+        }
+    }
+
+    /// Encodes the peer as a Bot API chat ID.
+    pub fn to_bot_api(&self) -> i64 {
+        match *self {
+            PeerId::User(raw) => raw as i64,
+            PeerId::Chat(raw) => -(raw as i64),
+            PeerId::Channel(raw) => ZERO_CHANNEL_ID - raw as i64,
+        }
+    }
+
+    /// The MTProto ID, as used by `t.me/c/<id>/…` links and client libraries.
+    pub fn raw(&self) -> u64 {
+        match *self {
+            PeerId::User(raw) | PeerId::Chat(raw) | PeerId::Channel(raw) => raw,
+        }
+    }
+
+    pub fn kind(&self) -> PeerKind {
+        match self {
+            PeerId::User(_) => PeerKind::User,
+            PeerId::Chat(_) => PeerKind::Group,
+            PeerId::Channel(_) => PeerKind::Channel,
+        }
+    }
+
+    /// Name of the MTProto constructor for this peer.
+    pub fn constructor(&self) -> &'static str {
+        match self {
+            PeerId::User(_) => "peerUser",
+            PeerId::Chat(_) => "peerChat",
+            PeerId::Channel(_) => "peerChannel",
+        }
+    }
+
+    /// The range of raw IDs this peer falls in.
+    pub fn range(&self) -> String {
+        match *self {
+            PeerId::User(raw) if raw <= LEGACY_ID_MAX => {
+                format!("1 – {} (32-bit users)", LEGACY_ID_MAX)
+            }
+            PeerId::User(_) => format!("{} – {} (64-bit users)", LEGACY_ID_MAX + 1, MAX_USER_ID),
+            PeerId::Chat(_) => format!("1 – {} (basic groups)", MAX_CHAT_ID),
+            PeerId::Channel(_) => format!("1 – {} (supergroups and channels)", MAX_CHANNEL_ID),
+        }
+    }
+}
+
+impl fmt::Display for PeerId {
+    /// `peerChannel 1234567890`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.constructor(), self.raw())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decodes `id` and checks that encoding gives it back.
+    fn decode(id: i64) -> Option<PeerId> {
+        let peer = PeerId::from_bot_api(id);
+        if let Some(peer) = peer {
+            assert_eq!(peer.to_bot_api(), id, "{:?} does not round-trip", peer);
+        }
+        peer
+    }
+
+    #[test]
+    fn users() {
+        assert_eq!(decode(1), Some(PeerId::User(1)));
+        assert_eq!(decode(MAX_USER_ID as i64), Some(PeerId::User(MAX_USER_ID)));
+        assert_eq!(decode(MAX_USER_ID as i64 + 1), None);
+        assert_eq!(decode(0), None);
+    }
+
+    #[test]
+    fn basic_groups() {
+        assert_eq!(decode(-1), Some(PeerId::Chat(1)));
+        assert_eq!(decode(-999_999_999_999), Some(PeerId::Chat(MAX_CHAT_ID)));
+    }
+
+    #[test]
+    fn channels() {
+        assert_eq!(decode(ZERO_CHANNEL_ID), None);
+        assert_eq!(decode(-1_000_000_000_001), Some(PeerId::Channel(1)));
+        assert_eq!(decode(ZERO_CHANNEL_ID - MAX_CHANNEL_ID as i64), Some(PeerId::Channel(MAX_CHANNEL_ID)));
+        assert_eq!(decode(ZERO_CHANNEL_ID - MAX_CHANNEL_ID as i64 - 1), None);
+    }
+
+    #[test]
+    fn outside_every_range() {
+        assert_eq!(decode(i64::MIN), None);
+        assert_eq!(decode(i64::MAX), None);
+    }
+}
@@ -0,0 +1,348 @@
+//! Renderers turning a [`Report`] into the Telegram HTML the bot sends.
+//!
+//! The user picks one with the `format` setting; `/json` always uses JSON.
+
+use std::{borrow::Cow, fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map};
+use teloxide::utils::html::{code_block_with_lang, code_inline, escape, link, user_mention};
+
+use crate::{
+    report::{Field, Report, Section, Value},
+    tree::{Node, Tree},
+};
+
+/// How an analysis is presented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    /// The box-drawing tree with tap-to-copy IDs.
+    #[default]
+    Tree,
+    /// Pretty-printed JSON in a code block.
+    Json,
+    /// YAML in a code block.
+    Yaml,
+    /// Only the IDs, on one line.
+    Compact,
+}
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 4] = [OutputFormat::Tree, OutputFormat::Json, OutputFormat::Yaml, OutputFormat::Compact];
+
+    pub fn renderer(&self) -> &'static dyn Renderer {
+        match self {
+            OutputFormat::Tree => &TreeRenderer,
+            OutputFormat::Json => &JsonRenderer,
+            OutputFormat::Yaml => &YamlRenderer,
+            OutputFormat::Compact => &CompactRenderer,
+        }
+    }
+
+    /// Renders `report` to fit into one message, leaving out the last items of
+    /// its longest lists as needed.
+    pub fn render(&self, report: &Report) -> String {
+        let renderer = self.renderer();
+        let mut report = Cow::Borrowed(report);
+        loop {
+            let text = renderer.render(&report);
+            if message_length(&text) <= MAX_MESSAGE_LENGTH || !shorten(report.to_mut()) {
+                return text;
+            }
+        }
+    }
+}
+
+/// Most characters Telegram accepts in one message, counted after the HTML is parsed.
+pub const MAX_MESSAGE_LENGTH: usize = 4096;
+
+/// Length of rendered HTML as Telegram counts it: visible text in UTF-16 code units.
+pub fn message_length(html: &str) -> usize {
+    html_to_plain(html).encode_utf16().count()
+}
+
+/// Items of a list that are shown, not counting the [`Value::More`] marker.
+fn shown(items: &[Value]) -> usize {
+    items.iter().filter(|item| !matches!(item, Value::More(_))).count()
+}
+
+/// Moves the last shown item of the longest list of the report into its
+/// [`Value::More`] count. Returns `false` if there is nothing left to drop.
+fn shorten(report: &mut Report) -> bool {
+    let longest = report
+        .sections
+        .iter_mut()
+        .flat_map(|section| section.fields.iter_mut())
+        .filter_map(|field| match &mut field.value {
+            Value::List(items) if shown(items) > 0 => Some(items),
+            _ => None,
+        })
+        .max_by_key(|items| shown(items));
+    let Some(items) = longest else {
+        return false;
+    };
+
+    let more = match items.last() {
+        Some(&Value::More(more)) => {
+            items.pop();
+            more
+        }
+        _ => 0,
+    };
+    items.pop();
+    items.push(Value::More(more + 1));
+    true
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OutputFormat::Tree => "tree",
+            OutputFormat::Json => "json",
+            OutputFormat::Yaml => "yaml",
+            OutputFormat::Compact => "compact",
+        })
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "tree" => Ok(OutputFormat::Tree),
+            "json" => Ok(OutputFormat::Json),
+            "yaml" => Ok(OutputFormat::Yaml),
+            "compact" => Ok(OutputFormat::Compact),
+            other => Err(format!("unknown output format: {}", other)),
+        }
+    }
+}
+
+/// Turns a report into Telegram HTML, ready to be sent with `ParseMode::Html`.
+pub trait Renderer: Send + Sync {
+    fn render(&self, report: &Report) -> String;
+}
+
+/// `… and 3 more`
+fn more_text(more: usize) -> String {
+    format!("… and {} more", more)
+}
+
+/// `👤 You` followed by a [`Tree`] of ` ├ key: value (note)` lines, sections
+/// separated by a blank line.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TreeRenderer;
+
+impl TreeRenderer {
+    fn value(value: &Value) -> String {
+        match value {
+            Value::Id(id) => code_inline(&id.to_string()),
+            Value::Code(code) => code_inline(code),
+            Value::Text(text) => escape(text),
+            Value::Bool(value) => value.to_string(),
+            Value::Number(number) => number.to_string(),
+            Value::Float(number) => number.to_string(),
+            Value::Mention { name, .. } if name.is_empty() => String::new(),
+            Value::Mention { user_id, name } => user_mention(*user_id, name),
+            Value::Link(url) => link(url, url),
+            Value::Peer(peer) => format!("{} {}", peer.constructor(), code_inline(&peer.raw().to_string())),
+            Value::Date(date) => date.format("%a, %d %b %Y %H:%M:%S GMT").to_string(),
+            Value::Age(estimate) => escape(&estimate.to_string()),
+            Value::None => "none".to_string(),
+            Value::More(more) => more_text(*more),
+            // Rendered as child nodes by `field`
+            Value::Fields(_) | Value::List(_) => String::new(),
+        }
+    }
+
+    /// A list item: the first of its fields labels the node and the rest hang
+    /// below it, e.g. an entity's type with its offset and length.
+    fn item(value: &Value) -> Node {
+        match value {
+            Value::Fields(fields) => match fields.split_first() {
+                Some((first, rest)) => Node {
+                    label: Self::field(first).label,
+                    children: rest.iter().map(Self::field).collect(),
+                },
+                None => Node::new(""),
+            },
+            value => Node::new(Self::value(value)),
+        }
+    }
+
+    fn field(field: &Field) -> Node {
+        let note = field.note.as_ref().map(|note| format!(" ({})", escape(note))).unwrap_or_default();
+        match &field.value {
+            Value::Fields(fields) => Node {
+                label: format!("{}{}", field.key, note),
+                children: fields.iter().map(Self::field).collect(),
+            },
+            Value::List(items) => Node {
+                label: format!("{}{}", field.key, note),
+                children: items.iter().map(Self::item).collect(),
+            },
+            value => Node::new(format!("{}: {}{}", field.key, Self::value(value), note)),
+        }
+    }
+
+    pub fn section(section: &Section) -> Tree {
+        Tree {
+            heading: format!("{} {}", section.icon, escape(&section.title)),
+            children: section.fields.iter().map(Self::field).collect(),
+        }
+    }
+}
+
+impl Renderer for TreeRenderer {
+    fn render(&self, report: &Report) -> String {
+        report.sections.iter().map(|section| Self::section(section).to_string()).collect::<Vec<_>>().join("\n")
+    }
+}
+
+/// The report as a JSON object keyed by section; sections sharing a key, such
+/// as several shared users, become an array.
+pub fn to_json(report: &Report) -> serde_json::Value {
+    let mut object = Map::new();
+    for section in &report.sections {
+        let fields = fields_to_json(&section.fields);
+
+        match object.get_mut(section.key) {
+            Some(serde_json::Value::Array(items)) => items.push(fields),
+            Some(existing) => *existing = json!([existing.take(), fields]),
+            None => {
+                object.insert(section.key.to_string(), fields);
+            }
+        }
+    }
+    serde_json::Value::Object(object)
+}
+
+fn fields_to_json(fields: &[Field]) -> serde_json::Value {
+    serde_json::Value::Object(
+        fields
+            .iter()
+            .map(|field| (field.key.to_string(), value_to_json(&field.value)))
+            .collect::<Map<_, _>>(),
+    )
+}
+
+fn value_to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Id(id) => json!(id),
+        Value::Code(text) | Value::Text(text) | Value::Link(text) => json!(text),
+        Value::Bool(value) => json!(value),
+        Value::Number(number) => json!(number),
+        Value::Float(number) => json!(number),
+        Value::Mention { name, .. } => json!(name),
+        Value::Peer(peer) => json!({ "constructor": peer.constructor(), "id": peer.raw() }),
+        Value::Date(date) => json!(date),
+        Value::Age(estimate) => json!(estimate),
+        Value::None => serde_json::Value::Null,
+        Value::More(more) => json!(more_text(*more)),
+        Value::Fields(fields) => fields_to_json(fields),
+        Value::List(items) => items.iter().map(value_to_json).collect(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonRenderer;
+
+impl Renderer for JsonRenderer {
+    fn render(&self, report: &Report) -> String {
+        let json = serde_json::to_string_pretty(&to_json(report)).expect("a JSON value always serializes");
+        code_block_with_lang(&json, "json")
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct YamlRenderer;
+
+impl Renderer for YamlRenderer {
+    fn render(&self, report: &Report) -> String {
+        let yaml = serde_yaml::to_string(&to_json(report)).expect("a JSON value always serializes");
+        code_block_with_lang(&yaml, "yaml")
+    }
+}
+
+/// `you: 123456789 | chat: -1001234567890`, every ID field of the report, numeric
+/// or string like a `file_id`, on one line.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompactRenderer;
+
+impl CompactRenderer {
+    fn collect_ids(section_key: &str, fields: &[Field], ids: &mut Vec<String>) {
+        for field in fields {
+            match &field.value {
+                // The main ID is labelled with its section, other IDs with their own name
+                Value::Id(id) if field.key == "id" => ids.push(format!("{}: {}", section_key, code_inline(&id.to_string()))),
+                Value::Id(id) => ids.push(format!("{}: {}", field.key, code_inline(&id.to_string()))),
+                // String IDs such as file_id and custom_emoji_id
+                Value::Code(id) if field.key.ends_with("_id") => ids.push(format!("{}: {}", field.key, code_inline(id))),
+                Value::Fields(fields) => Self::collect_ids(field.key, fields, ids),
+                Value::List(items) => {
+                    for item in items {
+                        if let Value::Fields(fields) = item {
+                            Self::collect_ids(field.key, fields, ids);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Renderer for CompactRenderer {
+    fn render(&self, report: &Report) -> String {
+        let mut ids = Vec::new();
+        for section in &report.sections {
+            Self::collect_ids(section.key, &section.fields, &mut ids);
+        }
+
+        if ids.is_empty() {
+            "no IDs".to_string()
+        } else {
+            ids.join(" | ")
+        }
+    }
+}
+
+/// Plain text of rendered HTML cut to [`MAX_MESSAGE_LENGTH`], the last resort
+/// when an answer is still too long for one message.
+pub fn truncated_plain(html: &str) -> String {
+    let plain = html_to_plain(html);
+    if plain.encode_utf16().count() <= MAX_MESSAGE_LENGTH {
+        return plain;
+    }
+
+    let mut truncated = String::new();
+    let mut length = 0;
+    for c in plain.chars() {
+        // Room for the closing ellipsis
+        if length + c.len_utf16() > MAX_MESSAGE_LENGTH - 1 {
+            break;
+        }
+        length += c.len_utf16();
+        truncated.push(c);
+    }
+    truncated.push('…');
+    truncated
+}
+
+/// Plain-text version of rendered HTML, sent when Telegram refuses to parse it.
+pub fn html_to_plain(html: &str) -> String {
+    let mut plain = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => plain.push(c),
+            _ => {}
+        }
+    }
+    // &amp; goes last so that an escaped "&lt;" does not turn into "<"
+    plain.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&amp;", "&")
+}
@@ -0,0 +1,114 @@
+//! Format-independent model of an analysis: sections of named fields that the
+//! renderers in [`crate::render`] turn into a tree, JSON, YAML or a one-liner.
+
+use chrono::{DateTime, Utc};
+use teloxide::types::UserId;
+
+use crate::{age::AgeEstimate, peer::PeerId};
+
+/// A single value, typed so each renderer can present it its own way.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// A user, chat or message ID. Copyable in the tree, listed by the compact format.
+    Id(i64),
+    /// A username or another string worth copying verbatim.
+    Code(String),
+    Text(String),
+    Bool(bool),
+    /// A size, duration or count; shown as is, unlike an [`Value::Id`].
+    Number(i64),
+    Float(f64),
+    /// A user's name linking to their account.
+    Mention { user_id: UserId, name: String },
+    Link(String),
+    Peer(PeerId),
+    Date(DateTime<Utc>),
+    Age(AgeEstimate),
+    /// A field that is known but has no value, e.g. a message outside any topic.
+    None,
+    /// Related fields grouped under one name, such as a chat's permissions.
+    Fields(Vec<Field>),
+    /// Several values of the same kind, such as the entities of a message.
+    List(Vec<Value>),
+    /// The last item of a [`Value::List`] that was cut short: how many items
+    /// were left out to keep the answer within one message.
+    More(usize),
+}
+
+/// A named value, optionally followed by a short human-readable explanation.
+/// Notes only appear in the tree; the data formats carry just the value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub key: &'static str,
+    pub value: Value,
+    pub note: Option<String>,
+}
+
+impl Field {
+    pub fn new(key: &'static str, value: Value) -> Self {
+        Field { key, value, note: None }
+    }
+
+    pub fn note(mut self, note: impl Into<String>) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+}
+
+/// One block of the analysis, such as the sender, the chat or a forward.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    /// Machine-readable name, used as the key in JSON and YAML (`you`, `chat`, …).
+    pub key: &'static str,
+    pub icon: &'static str,
+    /// Heading shown in the tree (`You`, `Forwarded from`, …).
+    pub title: String,
+    pub fields: Vec<Field>,
+}
+
+impl Section {
+    pub fn new(key: &'static str, icon: &'static str, title: impl Into<String>) -> Self {
+        Section { key, icon, title: title.into(), fields: Vec::new() }
+    }
+
+    pub fn field(mut self, field: Field) -> Self {
+        self.fields.push(field);
+        self
+    }
+
+    pub fn push(&mut self, field: Field) {
+        self.fields.push(field);
+    }
+}
+
+/// Everything the bot answers with for one request, in display order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    pub sections: Vec<Section>,
+}
+
+impl Report {
+    pub fn new() -> Self {
+        Report::default()
+    }
+
+    pub fn push(&mut self, section: Section) {
+        self.sections.push(section);
+    }
+
+    pub fn extend(&mut self, other: Report) {
+        self.sections.extend(other.sections);
+    }
+}
+
+impl From<Section> for Report {
+    fn from(section: Section) -> Self {
+        Report { sections: vec![section] }
+    }
+}
+
+impl FromIterator<Section> for Report {
+    fn from_iter<I: IntoIterator<Item = Section>>(iter: I) -> Self {
+        Report { sections: iter.into_iter().collect() }
+    }
+}
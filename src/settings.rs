@@ -0,0 +1,459 @@
+//! Per-chat and per-user preferences behind a pluggable [`SettingsStore`].
+//!
+//! The store is chosen with `SETTINGS_STORE`:
+//!
+//! - `memory` (default) - kept for the lifetime of the process
+//! - `sqlite:<path>` - a SQLite database, for the standalone server
+//! - `json:<path>` - a single JSON file, for serverless functions with a writable `/tmp`
+
+use std::{
+    collections::HashMap,
+    fmt, fs, io,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Mutex, OnceLock},
+};
+
+use serde::{Deserialize, Serialize};
+use teloxide::types::{ChatId, UserId};
+
+use crate::{group::GroupMode, render::OutputFormat};
+
+/// Environment variable selecting the settings backend.
+pub const SETTINGS_STORE_ENV: &str = "SETTINGS_STORE";
+
+static STORE: OnceLock<Box<dyn SettingsStore>> = OnceLock::new();
+
+/// How much of the analysis is shown for a regular message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verbosity {
+    /// Only the sender and, for forwards, the original sender.
+    Brief,
+    /// Sender, chat and forward details.
+    #[default]
+    Detailed,
+}
+
+impl Verbosity {
+    pub const ALL: [Verbosity; 2] = [Verbosity::Brief, Verbosity::Detailed];
+}
+
+impl fmt::Display for Verbosity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Verbosity::Brief => "brief",
+            Verbosity::Detailed => "detailed",
+        })
+    }
+}
+
+impl FromStr for Verbosity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "brief" => Ok(Verbosity::Brief),
+            "detailed" => Ok(Verbosity::Detailed),
+            other => Err(format!("unknown verbosity: {}", other)),
+        }
+    }
+}
+
+/// Preferences of one user or one chat. Missing fields fall back to their
+/// defaults, so stored settings survive new fields being added.
+///
+/// There is no language preference yet: every answer is English, so it would
+/// have nothing to choose between. It can join once answers are translated.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub verbosity: Verbosity,
+    /// How the analysis is rendered.
+    pub format: OutputFormat,
+    pub group_mode: GroupMode,
+}
+
+/// Whose settings these are. Users own their output preferences, chats own
+/// their group mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SettingsKey {
+    User(UserId),
+    Chat(ChatId),
+}
+
+impl SettingsKey {
+    fn scope(&self) -> &'static str {
+        match self {
+            SettingsKey::User(_) => "user",
+            SettingsKey::Chat(_) => "chat",
+        }
+    }
+
+    fn id(&self) -> i64 {
+        match *self {
+            SettingsKey::User(user_id) => user_id.0 as i64,
+            SettingsKey::Chat(chat_id) => chat_id.0,
+        }
+    }
+}
+
+impl fmt::Display for SettingsKey {
+    /// `user:123456789` or `chat:-1001234567890`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.scope(), self.id())
+    }
+}
+
+#[derive(Debug)]
+pub enum StoreError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    #[cfg(feature = "sqlite")]
+    Sqlite(rusqlite::Error),
+    InvalidBackend(String),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Io(err) => write!(f, "settings file error: {}", err),
+            StoreError::Json(err) => write!(f, "settings encoding error: {}", err),
+            #[cfg(feature = "sqlite")]
+            StoreError::Sqlite(err) => write!(f, "settings database error: {}", err),
+            StoreError::InvalidBackend(backend) => write!(f, "unknown settings store: {}", backend),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<io::Error> for StoreError {
+    fn from(err: io::Error) -> Self {
+        StoreError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for StoreError {
+    fn from(err: serde_json::Error) -> Self {
+        StoreError::Json(err)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for StoreError {
+    fn from(err: rusqlite::Error) -> Self {
+        StoreError::Sqlite(err)
+    }
+}
+
+/// A change to apply to stored settings with [`SettingsStore::update`].
+pub type SettingsChange = Box<dyn FnOnce(&mut Settings) + Send>;
+
+/// Persistence backend for [`Settings`]. Backends may block, so the bot only
+/// calls them through [`load`], [`update`] and [`effective`].
+pub trait SettingsStore: Send + Sync {
+    /// The stored settings, or `None` if nothing was saved for `key` yet.
+    fn load(&self, key: SettingsKey) -> Result<Option<Settings>, StoreError>;
+
+    fn save(&self, key: SettingsKey, settings: &Settings) -> Result<(), StoreError>;
+
+    /// Applies `change` to the settings of `key`, or to the defaults, and saves
+    /// them. No other update of the store can happen in between.
+    fn update(&self, key: SettingsKey, change: SettingsChange) -> Result<Settings, StoreError>;
+}
+
+/// Keeps settings for the lifetime of the process.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    settings: Mutex<HashMap<SettingsKey, Settings>>,
+}
+
+impl SettingsStore for MemoryStore {
+    fn load(&self, key: SettingsKey) -> Result<Option<Settings>, StoreError> {
+        Ok(self.settings.lock().unwrap().get(&key).cloned())
+    }
+
+    fn save(&self, key: SettingsKey, settings: &Settings) -> Result<(), StoreError> {
+        self.settings.lock().unwrap().insert(key, settings.clone());
+        Ok(())
+    }
+
+    fn update(&self, key: SettingsKey, change: SettingsChange) -> Result<Settings, StoreError> {
+        let mut all = self.settings.lock().unwrap();
+        let settings = all.entry(key).or_default();
+        change(settings);
+        Ok(settings.clone())
+    }
+}
+
+/// Keeps all settings in one JSON object keyed by `user:<id>`/`chat:<id>`.
+/// The file is read once and kept in memory; every save rewrites the whole
+/// file, which is fine for the handful of chats a serverless deployment sees
+/// between cold starts. Only one process may use the file at a time.
+#[derive(Debug)]
+pub struct JsonFileStore {
+    path: PathBuf,
+    /// The file's contents, once read.
+    cache: Mutex<Option<HashMap<String, Settings>>>,
+}
+
+impl JsonFileStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        JsonFileStore { path: path.into(), cache: Mutex::new(None) }
+    }
+
+    fn read(&self) -> Result<HashMap<String, Settings>, StoreError> {
+        match fs::read_to_string(&self.path) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn write(&self, all: &HashMap<String, Settings>) -> Result<(), StoreError> {
+        // Write to a temporary file first so a crash never leaves half a file behind
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(all)?)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    /// The cached settings, read from the file on first use.
+    fn cached<'a>(&self, cache: &'a mut Option<HashMap<String, Settings>>) -> Result<&'a mut HashMap<String, Settings>, StoreError> {
+        match cache {
+            Some(all) => Ok(all),
+            None => Ok(cache.insert(self.read()?)),
+        }
+    }
+
+    /// Applies `f` to the settings and writes them to the file. The cache only
+    /// takes the change once it is on disk.
+    fn modify<T>(&self, f: impl FnOnce(&mut HashMap<String, Settings>) -> T) -> Result<T, StoreError> {
+        let mut cache = self.cache.lock().unwrap();
+        let all = self.cached(&mut cache)?;
+        let mut changed = all.clone();
+        let result = f(&mut changed);
+        self.write(&changed)?;
+        *all = changed;
+        Ok(result)
+    }
+}
+
+impl SettingsStore for JsonFileStore {
+    fn load(&self, key: SettingsKey) -> Result<Option<Settings>, StoreError> {
+        let mut cache = self.cache.lock().unwrap();
+        Ok(self.cached(&mut cache)?.get(&key.to_string()).cloned())
+    }
+
+    fn save(&self, key: SettingsKey, settings: &Settings) -> Result<(), StoreError> {
+        self.modify(|all| {
+            all.insert(key.to_string(), settings.clone());
+        })
+    }
+
+    fn update(&self, key: SettingsKey, change: SettingsChange) -> Result<Settings, StoreError> {
+        self.modify(|all| {
+            let settings = all.entry(key.to_string()).or_default();
+            change(settings);
+            settings.clone()
+        })
+    }
+}
+
+/// Keeps settings in a SQLite table, one JSON document per user or chat.
+#[cfg(feature = "sqlite")]
+#[derive(Debug)]
+pub struct SqliteStore {
+    connection: Mutex<rusqlite::Connection>,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStore {
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self, StoreError> {
+        Self::with_connection(rusqlite::Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, StoreError> {
+        Self::with_connection(rusqlite::Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: rusqlite::Connection) -> Result<Self, StoreError> {
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS settings (
+                scope TEXT NOT NULL,
+                id INTEGER NOT NULL,
+                data TEXT NOT NULL,
+                PRIMARY KEY (scope, id)
+            )",
+            [],
+        )?;
+        Ok(SqliteStore { connection: Mutex::new(connection) })
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStore {
+    fn select(connection: &rusqlite::Connection, key: SettingsKey) -> Result<Option<Settings>, StoreError> {
+        use rusqlite::OptionalExtension;
+
+        let data: Option<String> = connection
+            .query_row(
+                "SELECT data FROM settings WHERE scope = ?1 AND id = ?2",
+                rusqlite::params![key.scope(), key.id()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(data.map(|data| serde_json::from_str(&data)).transpose()?)
+    }
+
+    fn upsert(connection: &rusqlite::Connection, key: SettingsKey, settings: &Settings) -> Result<(), StoreError> {
+        connection.execute(
+            "INSERT INTO settings (scope, id, data) VALUES (?1, ?2, ?3)
+             ON CONFLICT (scope, id) DO UPDATE SET data = excluded.data",
+            rusqlite::params![key.scope(), key.id(), serde_json::to_string(settings)?],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl SettingsStore for SqliteStore {
+    fn load(&self, key: SettingsKey) -> Result<Option<Settings>, StoreError> {
+        Self::select(&self.connection.lock().unwrap(), key)
+    }
+
+    fn save(&self, key: SettingsKey, settings: &Settings) -> Result<(), StoreError> {
+        Self::upsert(&self.connection.lock().unwrap(), key, settings)
+    }
+
+    fn update(&self, key: SettingsKey, change: SettingsChange) -> Result<Settings, StoreError> {
+        let mut connection = self.connection.lock().unwrap();
+        // Immediate, so another process sharing the database cannot write in between
+        let transaction = connection.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        let mut settings = Self::select(&transaction, key)?.unwrap_or_default();
+        change(&mut settings);
+        Self::upsert(&transaction, key, &settings)?;
+        transaction.commit()?;
+        Ok(settings)
+    }
+}
+
+/// Builds the store described by a `SETTINGS_STORE` value.
+pub fn store_from_spec(spec: &str) -> Result<Box<dyn SettingsStore>, StoreError> {
+    match spec.split_once(':') {
+        None if spec == "memory" => Ok(Box::new(MemoryStore::default())),
+        Some(("json", path)) => Ok(Box::new(JsonFileStore::new(path))),
+        #[cfg(feature = "sqlite")]
+        Some(("sqlite", path)) => Ok(Box::new(SqliteStore::open(path)?)),
+        _ => Err(StoreError::InvalidBackend(spec.to_string())),
+    }
+}
+
+/// Replaces the store used by the bot. Must be called before the first lookup;
+/// returns the store back if one is already in use.
+pub fn install(store: Box<dyn SettingsStore>) -> Result<(), Box<dyn SettingsStore>> {
+    STORE.set(store)
+}
+
+/// The store in use, created from `SETTINGS_STORE` on first access. A broken
+/// configuration is logged and settings are kept in memory instead.
+pub fn store() -> &'static dyn SettingsStore {
+    STORE
+        .get_or_init(|| {
+            let spec = std::env::var(SETTINGS_STORE_ENV).unwrap_or_else(|_| "memory".to_string());
+            store_from_spec(&spec).unwrap_or_else(|err| {
+                log::error!("{}, keeping settings in memory", err);
+                Box::new(MemoryStore::default())
+            })
+        })
+        .as_ref()
+}
+
+/// Runs `f` on the store on a blocking thread, keeping file and database I/O
+/// off the async runtime.
+async fn with_store<T: Send + 'static>(f: impl FnOnce(&'static dyn SettingsStore) -> T + Send + 'static) -> T {
+    tokio::task::spawn_blocking(move || f(store())).await.expect("settings store panicked")
+}
+
+/// Settings saved for `key`, or the defaults, with storage errors logged.
+fn load_or_default(store: &dyn SettingsStore, key: SettingsKey) -> Settings {
+    store.load(key).unwrap_or_else(|err| {
+        log::error!("Failed to load settings for {}: {}", key, err);
+        None
+    }).unwrap_or_default()
+}
+
+/// Settings saved for `key`, or the defaults. Storage errors are logged so a
+/// broken backend never stops the bot from answering.
+pub async fn load(key: SettingsKey) -> Settings {
+    with_store(move |store| load_or_default(store, key)).await
+}
+
+/// Applies `change` to the settings of `key` and saves them.
+pub async fn update(key: SettingsKey, change: impl FnOnce(&mut Settings) + Send + 'static) -> Result<Settings, StoreError> {
+    with_store(move |store| store.update(key, Box::new(change))).await
+}
+
+/// The settings that apply to a message: the sender's own preferences, with
+/// the group mode taken from the chat.
+pub async fn effective(chat_id: ChatId, user_id: Option<UserId>) -> Settings {
+    with_store(move |store| {
+        let mut settings = user_id.map(|user_id| load_or_default(store, SettingsKey::User(user_id))).unwrap_or_default();
+        settings.group_mode = load_or_default(store, SettingsKey::Chat(chat_id)).group_mode;
+        settings
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER: SettingsKey = SettingsKey::User(UserId(123_456_789));
+    const CHAT: SettingsKey = SettingsKey::Chat(ChatId(-1_001_234_567_890));
+
+    fn custom() -> Settings {
+        Settings { verbosity: Verbosity::Brief, format: OutputFormat::Yaml, group_mode: GroupMode::All }
+    }
+
+    /// Saves, loads and updates through `store`, checking users and chats stay apart.
+    fn round_trip(store: &dyn SettingsStore) {
+        assert_eq!(store.load(USER).unwrap(), None);
+
+        store.save(USER, &custom()).unwrap();
+        assert_eq!(store.load(USER).unwrap(), Some(custom()));
+        assert_eq!(store.load(CHAT).unwrap(), None);
+
+        let updated = store.update(CHAT, Box::new(|settings| settings.group_mode = GroupMode::Commands)).unwrap();
+        assert_eq!(updated, Settings { group_mode: GroupMode::Commands, ..Settings::default() });
+        assert_eq!(store.load(CHAT).unwrap(), Some(updated));
+
+        let updated = store.update(USER, Box::new(|settings| settings.format = OutputFormat::Compact)).unwrap();
+        assert_eq!(updated, Settings { format: OutputFormat::Compact, ..custom() });
+        assert_eq!(store.load(USER).unwrap(), Some(updated));
+    }
+
+    #[test]
+    fn memory_store_round_trip() {
+        round_trip(&MemoryStore::default());
+    }
+
+    #[test]
+    fn json_file_store_round_trip() {
+        let path = std::env::temp_dir().join(format!("telegram-id-settings-test-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        round_trip(&JsonFileStore::new(&path));
+        // A fresh store reads back what the first one wrote
+        assert_eq!(JsonFileStore::new(&path).load(USER).unwrap().map(|settings| settings.format), Some(OutputFormat::Compact));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn sqlite_store_round_trip() {
+        round_trip(&SqliteStore::open_in_memory().unwrap());
+    }
+}
@@ -0,0 +1,76 @@
+//! Box-drawing trees. Connectors are derived from each node's position when
+//! rendering, so optional fields can be added or left out freely:
+//!
+//! ```text
+//! 💬 Chat
+//!  ├ id: -1001234567890
+//!  ├ permissions
+//!  │  ├ can_send_messages: true
+//!  │  └ can_pin_messages: false
+//!  └ type: supergroup
+//! ```
+
+use std::fmt;
+
+/// A labelled entry with optional nested entries.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Node {
+    pub label: String,
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    pub fn new(label: impl Into<String>) -> Self {
+        Node { label: label.into(), children: Vec::new() }
+    }
+
+    pub fn child(mut self, node: Node) -> Self {
+        self.children.push(node);
+        self
+    }
+
+    pub fn push(&mut self, node: Node) {
+        self.children.push(node);
+    }
+}
+
+/// A heading line followed by its nodes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tree {
+    pub heading: String,
+    pub children: Vec<Node>,
+}
+
+impl Tree {
+    pub fn new(heading: impl Into<String>) -> Self {
+        Tree { heading: heading.into(), children: Vec::new() }
+    }
+
+    pub fn child(mut self, node: Node) -> Self {
+        self.children.push(node);
+        self
+    }
+
+    pub fn push(&mut self, node: Node) {
+        self.children.push(node);
+    }
+}
+
+fn write_nodes(f: &mut fmt::Formatter<'_>, nodes: &[Node], prefix: &str) -> fmt::Result {
+    for (i, node) in nodes.iter().enumerate() {
+        let last = i + 1 == nodes.len();
+        writeln!(f, "{}{}{}", prefix, if last { " └ " } else { " ├ " }, node.label)?;
+        // Descendants of the last node hang below empty space instead of a rail
+        let prefix = format!("{}{}", prefix, if last { "   " } else { " │ " });
+        write_nodes(f, &node.children, &prefix)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for Tree {
+    /// The heading and one line per node, each line ending in a newline.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.heading)?;
+        write_nodes(f, &self.children, "")
+    }
+}
@@ -0,0 +1,106 @@
+//! Snapshot tests for the JSON, YAML and compact renderers.
+//!
+//! Run `INSTA_UPDATE=always cargo test` after an intended change and review the
+//! diff in `tests/snapshots`.
+
+use insta::assert_snapshot;
+use teloxide::types::UserId;
+use telegram_id::{
+    peer::PeerId,
+    render::{
+        html_to_plain, message_length, to_json, truncated_plain, CompactRenderer, JsonRenderer, OutputFormat, Renderer, YamlRenderer,
+        MAX_MESSAGE_LENGTH,
+    },
+    report::{Field, Report, Section, Value},
+};
+
+/// A sender, a chat with nested permissions, a photo and two shared users,
+/// which share a section key.
+fn report() -> Report {
+    let shared = |id: i64, name: &str| {
+        Section::new("shared_user", "👤", "Shared user")
+            .field(Field::new("id", Value::Id(id)))
+            .field(Field::new("first_name", Value::Mention { user_id: UserId(id as u64), name: name.to_string() }))
+    };
+
+    Report::from_iter([
+        Section::new("you", "👤", "You")
+            .field(Field::new("id", Value::Id(123456789)))
+            .field(Field::new("first_name", Value::Mention { user_id: UserId(123456789), name: "Ada <3".to_string() }))
+            .field(Field::new("username", Value::Code("ada".to_string())))
+            .field(Field::new("language_code", Value::None).note("not shared")),
+        Section::new("chat", "💬", "Chat")
+            .field(Field::new("id", Value::Id(-1001234567890)))
+            .field(Field::new("mtproto_id", Value::Peer(PeerId::from_bot_api(-1001234567890).unwrap())))
+            .field(Field::new(
+                "permissions",
+                Value::Fields(vec![
+                    Field::new("can_send_messages", Value::Bool(true)),
+                    Field::new("can_pin_messages", Value::Bool(false)),
+                ]),
+            )),
+        Section::new("media", "📎", "Media")
+            .field(Field::new("type", Value::Text("photo".to_string())))
+            .field(Field::new("file_id", Value::Code("AgACAgIAAxkBAAI".to_string())))
+            .field(Field::new("file_size", Value::Number(64000)).note("62.5 KiB"))
+            .field(Field::new("sizes", Value::List(vec![Value::Text("90×90".to_string()), Value::Text("320×320".to_string())]))),
+        shared(42, "Grace"),
+        shared(43, "Alan"),
+    ])
+}
+
+#[test]
+fn json() {
+    assert_snapshot!(JsonRenderer.render(&report()));
+}
+
+#[test]
+fn yaml() {
+    assert_snapshot!(YamlRenderer.render(&report()));
+}
+
+#[test]
+fn compact() {
+    assert_snapshot!(CompactRenderer.render(&report()));
+}
+
+#[test]
+fn compact_without_ids() {
+    let report = Report::from(Section::new("media", "📎", "Media").field(Field::new("type", Value::Text("text".to_string()))));
+    assert_eq!(CompactRenderer.render(&report), "no IDs");
+}
+
+#[test]
+fn sections_sharing_a_key_become_an_array() {
+    let json = to_json(&report());
+    assert_eq!(json["shared_user"][0]["id"], 42);
+    assert_eq!(json["shared_user"][1]["first_name"], "Alan");
+    assert!(json["you"].is_object());
+
+    // A third section joins the existing array rather than nesting it
+    let mut report = report();
+    report.push(Section::new("shared_user", "👤", "Shared user").field(Field::new("id", Value::Id(44))));
+    assert_eq!(to_json(&report)["shared_user"].as_array().map(Vec::len), Some(3));
+}
+
+#[test]
+fn output_format_names_round_trip() {
+    for format in OutputFormat::ALL {
+        assert_eq!(format.to_string().parse::<OutputFormat>(), Ok(format));
+    }
+    assert!("xml".parse::<OutputFormat>().is_err());
+}
+
+#[test]
+fn plain_text_fallback_unescapes() {
+    assert_eq!(html_to_plain("<b>a &lt;b&gt; &amp;lt;</b> <code>1</code>"), "a <b> &lt; 1");
+}
+
+#[test]
+fn truncated_plain_fits_one_message() {
+    // Emoji count twice in UTF-16, which is what Telegram measures
+    let text = truncated_plain(&format!("<b>{}</b>", "👤".repeat(3000)));
+    assert_eq!(message_length(&text), MAX_MESSAGE_LENGTH - 1);
+    assert!(text.ends_with('…'));
+    assert_eq!(truncated_plain("<b>short</b>"), "short");
+}
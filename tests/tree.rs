@@ -0,0 +1,562 @@
+//! Snapshot tests for the tree output. Connectors must come out right for every
+//! combination of optional fields, so each case leaves out a different set.
+//!
+//! Run `INSTA_UPDATE=always cargo test` after an intended change and review the
+//! diff in `tests/snapshots`.
+
+use insta::assert_snapshot;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use teloxide::types::{Chat, Me, Message, MessageId, MessageOrigin, ThreadId, User, UserId};
+use telegram_id::{
+    format::{
+        chat_context_section, chat_full_section, chat_section, custom_emoji_section, entities_section, forward_message_section, forwarded_from_section,
+        id_section, media_section, member_section, message_report, profile_photos_section, push_profile_photos, replied_message_report,
+        sticker_set_reports, user_section,
+    },
+    render::{message_length, JsonRenderer, OutputFormat, Renderer, TreeRenderer, MAX_MESSAGE_LENGTH},
+    report::{Field, Report, Section, Value},
+    settings::Verbosity,
+    tree::{Node, Tree},
+};
+
+fn render(section: Section) -> String {
+    TreeRenderer.render(&Report::from(section))
+}
+
+fn user(last_name: Option<&str>, username: Option<&str>, language_code: Option<&str>) -> User {
+    User {
+        id: UserId(123_456_789),
+        is_bot: false,
+        first_name: "Ada".to_string(),
+        last_name: last_name.map(str::to_string),
+        username: username.map(str::to_string),
+        language_code: language_code.map(str::to_string),
+        is_premium: false,
+        added_to_attachment_menu: false,
+    }
+}
+
+fn chat(value: serde_json::Value) -> Chat {
+    serde_json::from_value(value).expect("valid chat")
+}
+
+/// Deserializes `base` with the fields of `extra` added or replaced.
+fn merged<T: DeserializeOwned>(mut base: serde_json::Value, extra: serde_json::Value) -> T {
+    base.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+    serde_json::from_value(base).expect("valid fixture")
+}
+
+/// A private message from Ada with `extra` merged in, e.g. an attachment.
+fn message(extra: serde_json::Value) -> Message {
+    let base = json!({
+        "message_id": 1,
+        "date": 1_600_000_000,
+        "chat": {"id": 123456789, "type": "private", "first_name": "Ada"},
+        "from": {"id": 123456789, "is_bot": false, "first_name": "Ada"},
+    });
+    merged(base, extra)
+}
+
+#[test]
+fn nested_nodes() {
+    let tree = Tree::new("💬 Chat")
+        .child(Node::new("id: 1"))
+        .child(
+            Node::new("permissions")
+                .child(Node::new("can_send_messages: true"))
+                .child(Node::new("media").child(Node::new("can_send_photos: false")))
+                .child(Node::new("can_pin_messages: false")),
+        )
+        .child(Node::new("flags").child(Node::new("is_forum: true")));
+    assert_snapshot!(tree.to_string());
+}
+
+#[test]
+fn empty_tree() {
+    assert_snapshot!(Tree::new("💬 Chat").to_string());
+}
+
+#[test]
+fn nested_fields() {
+    let section = Section::new("chat", "💬", "Chat")
+        .field(Field::new("id", Value::Id(-1001234567890)))
+        .field(Field::new(
+            "permissions",
+            Value::Fields(vec![
+                Field::new("can_send_messages", Value::Bool(true)),
+                Field::new("can_pin_messages", Value::Bool(false)),
+            ]),
+        ));
+    assert_snapshot!(render(section));
+}
+
+#[test]
+fn user_minimal() {
+    assert_snapshot!(render(user_section(&user(None, None, None), "you", "You", None)));
+}
+
+#[test]
+fn user_full() {
+    let user = user(Some("Lovelace"), Some("ada"), Some("en"));
+    assert_snapshot!(render(user_section(&user, "you", "You", None)));
+}
+
+#[test]
+fn user_with_username_only() {
+    assert_snapshot!(render(user_section(&user(None, Some("ada"), None), "you", "You", None)));
+}
+
+#[test]
+fn user_premium_in_attachment_menu() {
+    let mut user = user(None, Some("ada"), None);
+    user.is_premium = true;
+    user.added_to_attachment_menu = true;
+    assert_snapshot!(render(user_section(&user, "you", "You", None)));
+}
+
+#[test]
+fn other_bot() {
+    let mut bot = user(None, Some("other_bot"), None);
+    bot.is_bot = true;
+    assert_snapshot!(render(user_section(&bot, "sender", "Sender", None)));
+}
+
+#[test]
+fn this_bot() {
+    let me: Me = serde_json::from_value(json!({
+        "id": 987654321,
+        "is_bot": true,
+        "first_name": "ID Bot",
+        "username": "id_bot",
+        "can_join_groups": true,
+        "can_read_all_group_messages": false,
+        "supports_inline_queries": true,
+        "can_connect_to_business": false,
+        "has_main_web_app": false,
+    }))
+    .expect("valid me");
+    assert_snapshot!(render(user_section(&me.user, "sender", "Sender", Some(&me))));
+}
+
+#[test]
+fn user_escapes_names() {
+    let mut user = user(Some("<b>&</b>"), None, None);
+    user.first_name = "A <i>".to_string();
+    assert_snapshot!(render(user_section(&user, "sender", "Sender", None)));
+}
+
+#[test]
+fn chat_private() {
+    assert_snapshot!(render(chat_section(&chat(json!({"id": 123456789, "type": "private", "first_name": "Ada"})))));
+}
+
+#[test]
+fn chat_private_with_username() {
+    let chat = chat(json!({"id": 123456789, "type": "private", "first_name": "Ada", "username": "ada"}));
+    assert_snapshot!(render(chat_section(&chat)));
+}
+
+#[test]
+fn chat_group() {
+    assert_snapshot!(render(chat_section(&chat(json!({"id": -123456789, "type": "group", "title": "Friends"})))));
+}
+
+#[test]
+fn chat_supergroup_with_username() {
+    let chat = chat(json!({"id": -1001234567890i64, "type": "supergroup", "title": "Rust", "username": "rust"}));
+    assert_snapshot!(render(chat_section(&chat)));
+}
+
+#[test]
+fn chat_channel() {
+    assert_snapshot!(render(chat_section(&chat(json!({"id": -1001234567890i64, "type": "channel", "title": "News"})))));
+}
+
+#[test]
+fn id_user() {
+    assert_snapshot!(render(id_section(123_456_789)));
+}
+
+#[test]
+fn id_group() {
+    assert_snapshot!(render(id_section(-123_456_789)));
+}
+
+#[test]
+fn id_channel() {
+    assert_snapshot!(render(id_section(-1_001_234_567_890)));
+}
+
+#[test]
+fn id_out_of_range() {
+    assert_snapshot!(render(id_section(0)));
+}
+
+#[test]
+fn forwarded_from_hidden_user() {
+    let origin = MessageOrigin::HiddenUser {
+        date: chrono::DateTime::from_timestamp(1_600_000_000, 0).unwrap(),
+        sender_user_name: "Anonymous".to_string(),
+    };
+    assert_snapshot!(render(forwarded_from_section(&origin, None)));
+}
+
+#[test]
+fn forwarded_from_channel() {
+    let origin = MessageOrigin::Channel {
+        date: chrono::DateTime::from_timestamp(1_600_000_000, 0).unwrap(),
+        chat: chat(json!({"id": -1001234567890i64, "type": "channel", "title": "News", "username": "news"})),
+        message_id: MessageId(42),
+        author_signature: Some("Editor".to_string()),
+    };
+    assert_snapshot!(render(forwarded_from_section(&origin, None)));
+    assert_snapshot!(render(forward_message_section(&origin, true)));
+}
+
+#[test]
+fn forward_message_without_link() {
+    let origin = MessageOrigin::User {
+        date: chrono::DateTime::from_timestamp(1_600_000_000, 0).unwrap(),
+        sender_user: user(None, None, None),
+    };
+    assert_snapshot!(render(forward_message_section(&origin, false)));
+}
+
+#[test]
+fn context_in_topic_with_linked_chat() {
+    assert_snapshot!(render(chat_context_section(Some(ThreadId(MessageId(7))), Some(-1001234567890))));
+}
+
+#[test]
+fn context_without_topic_or_linked_chat() {
+    assert_snapshot!(render(chat_context_section(None, None)));
+}
+
+#[test]
+fn several_sections() {
+    let report = Report::from_iter([id_section(123_456_789), chat_context_section(None, None)]);
+    assert_snapshot!(TreeRenderer.render(&report));
+}
+
+#[test]
+fn media_photo() {
+    let msg = message(json!({"photo": [
+        {"file_id": "small", "file_unique_id": "s", "file_size": 1200, "width": 90, "height": 51},
+        {"file_id": "large", "file_unique_id": "l", "file_size": 1_572_864, "width": 1280, "height": 720},
+    ]}));
+    assert_snapshot!(render(media_section(&msg).unwrap()));
+}
+
+#[test]
+fn media_document_without_mime_type() {
+    let msg = message(json!({"document": {"file_id": "doc", "file_unique_id": "d", "file_size": 512, "file_name": "notes.txt"}}));
+    assert_snapshot!(render(media_section(&msg).unwrap()));
+}
+
+#[test]
+fn media_voice() {
+    let msg = message(json!({"voice": {"file_id": "voice", "file_unique_id": "v", "file_size": 20480, "duration": 75, "mime_type": "audio/ogg"}}));
+    assert_snapshot!(render(media_section(&msg).unwrap()));
+}
+
+#[test]
+fn media_sticker_without_set() {
+    let msg = message(json!({"sticker": {
+        "file_id": "sticker", "file_unique_id": "st", "file_size": 30000, "width": 512, "height": 512,
+        "type": "regular", "is_animated": false, "is_video": false,
+    }}));
+    assert_snapshot!(render(media_section(&msg).unwrap()));
+}
+
+#[test]
+fn media_text_message() {
+    assert_eq!(media_section(&message(json!({"text": "hi"}))), None);
+}
+
+#[test]
+fn media_mask_sticker() {
+    let msg = message(json!({"sticker": {
+        "file_id": "mask", "file_unique_id": "m", "file_size": 4096, "width": 512, "height": 512,
+        "type": "mask", "is_animated": false, "is_video": false, "emoji": "😎", "set_name": "masks",
+        "mask_position": {"point": "eyes", "x_shift": -0.5, "y_shift": 0.25, "scale": 1.5},
+    }}));
+    assert_snapshot!(render(media_section(&msg).unwrap()));
+}
+
+#[test]
+fn media_premium_sticker() {
+    let msg = message(json!({"sticker": {
+        "file_id": "premium", "file_unique_id": "p", "file_size": 60000, "width": 512, "height": 512,
+        "type": "regular", "is_animated": true, "is_video": false, "emoji": "🎉", "set_name": "party",
+        "premium_animation": {"file_id": "effect", "file_unique_id": "e", "file_size": 90000},
+    }}));
+    assert_snapshot!(render(media_section(&msg).unwrap()));
+}
+
+#[test]
+fn custom_emoji_in_caption() {
+    let msg = message(json!({
+        "caption": "a 🙂 b 🔥",
+        "caption_entities": [
+            {"type": "bold", "offset": 0, "length": 1},
+            {"type": "custom_emoji", "offset": 2, "length": 2, "custom_emoji_id": "5368324170671202286"},
+            {"type": "custom_emoji", "offset": 7, "length": 2, "custom_emoji_id": "5420315771991497307"},
+        ],
+        "photo": [{"file_id": "p", "file_unique_id": "p", "file_size": 1, "width": 1, "height": 1}],
+    }));
+    let section = custom_emoji_section(&msg).unwrap();
+    assert_snapshot!(render(section.clone()));
+    assert_snapshot!(JsonRenderer.render(&section.into()));
+}
+
+#[test]
+fn custom_emoji_absent() {
+    let msg = message(json!({"text": "hi", "entities": [{"type": "bold", "offset": 0, "length": 2}]}));
+    assert_eq!(custom_emoji_section(&msg), None);
+}
+
+#[test]
+fn sticker_set_pages() {
+    let stickers = (0..60)
+        .map(|i| json!({
+            "file_id": format!("file{}", i), "file_unique_id": format!("u{}", i), "file_size": 100,
+            "width": 512, "height": 512, "type": "regular", "is_animated": false, "is_video": false, "emoji": "👍",
+        }))
+        .collect::<Vec<_>>();
+    let set = serde_json::from_value(json!({"name": "thumbs", "title": "Thumbs", "sticker_type": "regular", "stickers": stickers})).unwrap();
+    let reports = sticker_set_reports(&set, OutputFormat::Tree);
+    assert_eq!(reports.len(), 2);
+    assert_snapshot!(OutputFormat::Tree.render(&reports[1]));
+    // Compact lists only the IDs, so the whole set fits into one message
+    let reports = sticker_set_reports(&set, OutputFormat::Compact);
+    assert_eq!(reports.len(), 1);
+    assert_snapshot!(OutputFormat::Compact.render(&reports[0]));
+}
+
+#[test]
+fn sticker_set_pages_fit_one_message() {
+    // Custom emoji carry a third ID, and real file_ids are about this long
+    let stickers = (0..50)
+        .map(|i| json!({
+            "file_id": format!("{:0>72}", i), "file_unique_id": format!("AgADbBsAAl{:0>6}", i), "file_size": 100,
+            "width": 100, "height": 100, "type": "custom_emoji", "custom_emoji_id": format!("53683241706712{:0>5}", i),
+            "is_animated": false, "is_video": false, "emoji": "👍",
+        }))
+        .collect::<Vec<_>>();
+    let set = serde_json::from_value(json!({"name": "emoji", "title": "Emoji", "sticker_type": "custom_emoji", "stickers": stickers})).unwrap();
+
+    for format in OutputFormat::ALL {
+        let pages = sticker_set_reports(&set, format).iter().map(|report| format.render(report)).collect::<Vec<_>>();
+        for page in &pages {
+            assert!(message_length(page) <= MAX_MESSAGE_LENGTH, "{} page is {} characters long", format, message_length(page));
+        }
+        // Every sticker shows up exactly once across the pages
+        let listed = pages.iter().map(|page| page.matches(&"0".repeat(60)).count()).sum::<usize>();
+        assert_eq!(listed, 50, "{}", format);
+    }
+}
+
+#[test]
+fn entities_of_every_kind() {
+    let msg = message(json!({
+        "text": "/id @ada #rust link Ada x := 1 🙂 and a very long quote that goes on and on and on well past the limit",
+        "entities": [
+            {"type": "bot_command", "offset": 0, "length": 3},
+            {"type": "mention", "offset": 4, "length": 4},
+            {"type": "hashtag", "offset": 9, "length": 5},
+            {"type": "text_link", "offset": 15, "length": 4, "url": "https://example.com/"},
+            {"type": "text_mention", "offset": 20, "length": 3, "user": {"id": 42, "is_bot": false, "first_name": "Ada"}},
+            {"type": "pre", "offset": 24, "length": 6, "language": "rust"},
+            {"type": "custom_emoji", "offset": 31, "length": 2, "custom_emoji_id": "5368324170671202286"},
+            {"type": "blockquote", "offset": 34, "length": 70},
+        ],
+    }));
+    assert_snapshot!(render(entities_section(&msg).unwrap()));
+}
+
+#[test]
+fn entities_capped() {
+    // Twenty bold words, more than one answer lists
+    let text = "word ".repeat(20);
+    let entities = (0..20).map(|word| json!({"type": "bold", "offset": word * 5, "length": 4})).collect::<Vec<_>>();
+    let msg = message(json!({"text": text, "entities": entities}));
+    assert_snapshot!(render(entities_section(&msg).unwrap()));
+}
+
+#[test]
+fn custom_emoji_beyond_the_entity_cap() {
+    // Sixteen bold words fill the entity list, so the custom emoji after them
+    // is listed on its own
+    let text = format!("{}🙂", "word ".repeat(16));
+    let mut entities = (0..16).map(|word| json!({"type": "bold", "offset": word * 5, "length": 4})).collect::<Vec<_>>();
+    entities.push(json!({"type": "custom_emoji", "offset": 80, "length": 2, "custom_emoji_id": "5368324170671202286"}));
+    let msg = message(json!({"text": text, "entities": entities}));
+
+    let report = message_report(&msg, Verbosity::Detailed, None);
+    assert!(report.sections.iter().any(|section| section.key == "custom_emoji"));
+    assert!(OutputFormat::Tree.render(&report).contains("5368324170671202286"));
+    let replied = replied_message_report(&msg, None);
+    assert!(OutputFormat::Json.render(&replied).contains("5368324170671202286"));
+
+    // With room for every entity, the custom emoji is not listed twice
+    let msg = message(json!({"text": "🙂", "entities": [{"type": "custom_emoji", "offset": 0, "length": 2, "custom_emoji_id": "5368324170671202286"}]}));
+    assert_eq!(OutputFormat::Tree.render(&message_report(&msg, Verbosity::Detailed, None)).matches("5368324170671202286").count(), 1);
+}
+
+#[test]
+fn capped_report_fits_one_message() {
+    // Twenty text links and forty long text mentions: few enough entities, but
+    // too long to list them all
+    let entities = |count: usize, length: usize, entity: &dyn Fn(usize) -> serde_json::Value| {
+        let text = format!("{} ", "w".repeat(length)).repeat(count);
+        let entities = (0..count)
+            .map(|i| merged::<serde_json::Value>(entity(i), json!({"offset": i * (length + 1), "length": length})))
+            .collect::<Vec<_>>();
+        message(json!({"text": text, "entities": entities}))
+    };
+    let links = entities(20, 30, &|_| json!({"type": "text_link", "url": format!("https://example.com/{}", "x".repeat(40))}));
+    let mentions = entities(40, 60, &|i| json!({"type": "text_mention", "user": {"id": 1000 + i, "is_bot": false, "first_name": "m".repeat(64)}}));
+
+    for msg in [links, mentions] {
+        let report = message_report(&msg, Verbosity::Detailed, None);
+        for format in OutputFormat::ALL {
+            let length = message_length(&format.render(&report));
+            assert!(length <= MAX_MESSAGE_LENGTH, "{} answer is {} characters long", format, length);
+        }
+    }
+}
+
+#[test]
+fn entities_absent() {
+    assert_eq!(entities_section(&message(json!({"text": "plain"}))), None);
+}
+
+fn chat_full(value: serde_json::Value) -> teloxide::types::ChatFullInfo {
+    let base = json!({
+        "max_reaction_count": 11,
+        "accent_color_id": 3,
+        "accepted_gift_types": {
+            "unlimited_gifts": true, "limited_gifts": true, "unique_gifts": true, "premium_subscription": true,
+        },
+    });
+    merged(base, value)
+}
+
+#[test]
+fn chat_full_supergroup() {
+    let info = chat_full(json!({
+        "id": -1001234567890i64,
+        "type": "supergroup",
+        "title": "Rust",
+        "username": "rust",
+        "active_usernames": ["rust", "rustlang"],
+        "description": "All things <Rust>",
+        "invite_link": "https://t.me/+abcdef",
+        "linked_chat_id": -1009876543210i64,
+        "permissions": {"can_send_messages": true, "can_send_photos": true, "can_pin_messages": false},
+        "slow_mode_delay": 30,
+        "location": {"location": {"latitude": 52.52, "longitude": 13.405}, "address": "Berlin"},
+        "join_to_send_messages": true,
+        "has_protected_content": true,
+    }));
+    assert_snapshot!(render(chat_full_section(&info)));
+}
+
+#[test]
+fn chat_full_private() {
+    let info = chat_full(json!({
+        "id": 123456789,
+        "type": "private",
+        "first_name": "Ada",
+        "bio": "Analyst",
+        "emoji_status_custom_emoji_id": "5368324170671202286",
+        "message_auto_delete_time": 86400,
+    }));
+    assert_snapshot!(render(chat_full_section(&info)));
+}
+
+/// Ada as a member of a group, with `value` giving her status and its fields.
+fn member(value: serde_json::Value) -> teloxide::types::ChatMember {
+    merged(json!({"user": {"id": 123456789, "is_bot": false, "first_name": "Ada"}}), value)
+}
+
+#[test]
+fn member_owner() {
+    let member = member(json!({"status": "creator", "is_anonymous": false, "custom_title": "Founder"}));
+    assert_snapshot!(render(member_section(&member)));
+}
+
+#[test]
+fn member_administrator() {
+    let member = member(json!({
+        "status": "administrator",
+        "custom_title": "Mod",
+        "is_anonymous": false,
+        "can_be_edited": true,
+        "can_manage_chat": true,
+        "can_delete_messages": true,
+        "can_manage_video_chats": false,
+        "can_restrict_members": true,
+        "can_promote_members": false,
+        "can_change_info": false,
+        "can_invite_users": true,
+        "can_post_stories": false,
+        "can_edit_stories": false,
+        "can_delete_stories": false,
+        "can_pin_messages": true,
+        "can_manage_topics": false,
+    }));
+    assert_snapshot!(render(member_section(&member)));
+}
+
+#[test]
+fn member_restricted() {
+    let member = member(json!({
+        "status": "restricted",
+        "is_member": true,
+        "can_send_messages": true,
+        "can_send_audios": false,
+        "can_send_documents": false,
+        "can_send_photos": true,
+        "can_send_videos": false,
+        "can_send_video_notes": false,
+        "can_send_voice_notes": false,
+        "can_send_polls": false,
+        "can_send_other_messages": false,
+        "can_add_web_page_previews": false,
+        "can_change_info": false,
+        "can_invite_users": true,
+        "can_pin_messages": false,
+        "can_manage_topics": false,
+        "until_date": 1_700_000_000,
+    }));
+    assert_snapshot!(render(member_section(&member)));
+}
+
+#[test]
+fn member_banned_forever() {
+    let member = member(json!({"status": "kicked", "until_date": 0}));
+    assert_snapshot!(render(member_section(&member)));
+}
+
+#[test]
+fn profile_photos() {
+    let size = |id: &str, side: u32| json!({"file_id": id, "file_unique_id": format!("{}-u", id), "width": side, "height": side, "file_size": side * 100});
+    let photos = serde_json::from_value(json!({
+        "total_count": 12,
+        "photos": [
+            [size("AgAD1a", 160), size("AgAD1b", 320), size("AgAD1c", 640)],
+            [size("AgAD2a", 160), size("AgAD2b", 640)],
+        ],
+    }))
+    .expect("valid profile photos");
+    assert_snapshot!(render(profile_photos_section(UserId(123_456_789), &photos)));
+}
+
+#[test]
+fn profile_photo_count_in_user_section() {
+    let mut report = Report::from(user_section(&user(None, Some("ada"), None), "you", "You", None));
+    push_profile_photos(&mut report, UserId(123_456_789), 0);
+    assert_snapshot!(TreeRenderer.render(&report));
+}